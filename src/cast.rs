@@ -0,0 +1,350 @@
+//! asciicast v2 recordings, for asciinema and friends: writing the output
+//! as one, and playing one back through a [`Printer`].
+
+use std::{
+    io::{self, BufRead, Write},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Printer, StreamError,
+    json::{Json, quote},
+    stream::consume_bytes,
+};
+
+/// The time line animation frames are scheduled on, and recordings are
+/// stamped with.
+///
+/// A real clock follows the wall clock and sleeps until each frame is due; a
+/// simulated one jumps straight to it. While an animation runs the clock
+/// reads the time the current frame was scheduled for, so a recording's
+/// frames land exactly `1 / --speed` seconds apart. Clones share one time
+/// line.
+#[derive(Clone, Debug)]
+pub struct Clock(Arc<Mutex<ClockState>>);
+
+#[derive(Debug)]
+struct ClockState {
+    start: Instant,
+    simulated: bool,
+    /// The scheduled time, and whether it holds over the wall clock.
+    at: Duration,
+    pinned: bool,
+}
+
+impl Clock {
+    /// A clock that follows the wall clock from now on.
+    pub fn real() -> Self {
+        Self::new(false)
+    }
+
+    /// A clock that starts at zero and never sleeps, `--record-only`.
+    pub fn simulated() -> Self {
+        Self::new(true)
+    }
+
+    fn new(simulated: bool) -> Self {
+        Self(Arc::new(Mutex::new(ClockState {
+            start: Instant::now(),
+            simulated,
+            at: Duration::ZERO,
+            pinned: false,
+        })))
+    }
+
+    /// Time since the clock started.
+    pub fn elapsed(&self) -> Duration {
+        let state = self.state();
+        if state.simulated || state.pinned {
+            state.at
+        } else {
+            state.start.elapsed().max(state.at)
+        }
+    }
+
+    /// Holds the clock at the current time for the first frame of an
+    /// animation, and returns that time.
+    pub(crate) fn pin(&self) -> Duration {
+        let at = self.elapsed();
+        let mut state = self.state();
+        state.at = at;
+        state.pinned = true;
+        at
+    }
+
+    /// Waits until `at`, or returns at once when already late or simulated,
+    /// then holds the clock at the time the next frame goes out.
+    pub(crate) fn sleep_until(&self, at: Duration) -> Duration {
+        let (start, simulated) = {
+            let state = self.state();
+            (state.start, state.simulated)
+        };
+        let at = if simulated {
+            at
+        } else {
+            let now = start.elapsed();
+            if at > now {
+                thread::sleep(at - now);
+                at
+            } else {
+                now
+            }
+        };
+        let mut state = self.state();
+        state.at = at;
+        state.pinned = true;
+        at
+    }
+
+    /// Lets a real clock follow the wall clock again once an animation ends.
+    pub(crate) fn release(&self) {
+        self.state().pinned = false;
+    }
+
+    fn state(&self) -> MutexGuard<'_, ClockState> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::real()
+    }
+}
+
+/// [`Write`] adapter that records everything written through it as the
+/// output events of an asciicast v2 file, stamped by a [`Clock`].
+///
+/// Writes at the same time join one event. A UTF-8 sequence split across
+/// writes is held back until it is complete; dropping the writer (or
+/// calling [`finish`](CastWriter::finish)) records whatever is left.
+pub struct CastWriter<W: Write> {
+    inner: Option<W>,
+    clock: Clock,
+    stamp: Duration,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> CastWriter<W> {
+    /// Writes the header for a `width` by `height` terminal.
+    pub fn new(mut inner: W, width: usize, height: usize, clock: Clock) -> io::Result<Self> {
+        writeln!(
+            inner,
+            "{{\"version\": 2, \"width\": {width}, \"height\": {height}}}"
+        )?;
+        Ok(Self {
+            inner: Some(inner),
+            clock,
+            stamp: Duration::ZERO,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Records anything pending and hands back the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.record(true)?;
+        let mut inner = self.inner.take().expect("writer already finished");
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    /// Writes the pending bytes as one event, keeping back an unfinished
+    /// UTF-8 sequence unless `all`.
+    fn record(&mut self, all: bool) -> io::Result<()> {
+        let keep = match std::str::from_utf8(&self.pending) {
+            Err(err) if !all && err.error_len().is_none() => self.pending.len() - err.valid_up_to(),
+            _ => 0,
+        };
+        let end = self.pending.len() - keep;
+        if end == 0 {
+            return Ok(());
+        }
+        let data = String::from_utf8_lossy(&self.pending[..end]);
+        let line = format!(
+            "[{:.6}, \"o\", {}]\n",
+            self.stamp.as_secs_f64(),
+            quote(&data)
+        );
+        self.pending.drain(..end);
+        match self.inner.as_mut() {
+            Some(inner) => inner.write_all(line.as_bytes()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Write for CastWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = self.clock.elapsed();
+        if now != self.stamp {
+            self.record(false)?;
+            self.stamp = now;
+        }
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.record(false)?;
+        match self.inner.as_mut() {
+            Some(inner) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for CastWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.record(true);
+            if let Some(inner) = self.inner.as_mut() {
+                let _ = inner.flush();
+            }
+        }
+    }
+}
+
+/// Replays the output events of an asciicast v2 recording through each
+/// printer into its writer, every event at its recorded time divided by
+/// `speed` on the first printer's clock. Escape sequences may span events,
+/// as they did on the terminal.
+pub fn play_cast<R: BufRead>(
+    reader: R,
+    outputs: &mut [(&mut Printer, &mut dyn Write)],
+    speed: f64,
+) -> Result<(), StreamError> {
+    let clock = match outputs.first() {
+        Some((printer, _)) => printer.clock.clone(),
+        None => return Ok(()),
+    };
+    let start = clock.pin();
+    let mut header = false;
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |what: &str| {
+            let message = format!("line {}: {what}", idx + 1);
+            StreamError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+        };
+        let value = Json::parse(&line).map_err(|err| invalid(&err))?;
+        if !header {
+            if value.get("version").and_then(Json::as_f64) != Some(2.0) {
+                return Err(invalid("not an asciicast v2 recording"));
+            }
+            header = true;
+            continue;
+        }
+        let (time, data) = match &value {
+            Json::Array(event) => match event.as_slice() {
+                [Json::Number(time), Json::String(kind), Json::String(data)] => {
+                    if kind != "o" {
+                        continue;
+                    }
+                    (*time, data)
+                }
+                _ => return Err(invalid("expected [time, type, data]")),
+            },
+            _ => return Err(invalid("expected [time, type, data]")),
+        };
+        let due = Duration::try_from_secs_f64(time.max(0.0) / speed)
+            .ok()
+            .and_then(|offset| start.checked_add(offset))
+            .ok_or_else(|| invalid("timestamp out of range"))?;
+        clock.sleep_until(due);
+        for (printer, writer) in outputs.iter_mut() {
+            if printer.use_color {
+                consume_bytes(data.as_bytes(), printer, *writer)?;
+                printer.flush_pending(*writer)?;
+            } else {
+                writer.write_all(data.as_bytes())?;
+            }
+            writer.flush()?;
+        }
+    }
+    clock.release();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorMode, Config};
+
+    #[test]
+    fn events_follow_the_clock_and_keep_utf8_whole() {
+        let clock = Clock::simulated();
+        let mut cast = CastWriter::new(Vec::new(), 80, 24, clock.clone()).unwrap();
+        cast.write_all(b"a\xc3").unwrap();
+        clock.sleep_until(Duration::from_millis(50));
+        cast.write_all(b"\xa9b").unwrap();
+        cast.write_all(b"c").unwrap();
+        clock.release();
+        let text = String::from_utf8(cast.finish().unwrap()).unwrap();
+        assert_eq!(
+            text,
+            "{\"version\": 2, \"width\": 80, \"height\": 24}\n\
+             [0.000000, \"o\", \"a\"]\n\
+             [0.050000, \"o\", \"\u{e9}bc\"]\n"
+        );
+    }
+
+    #[test]
+    fn playback_keeps_timing_and_escape_state() {
+        let recording = concat!(
+            "{\"version\": 2, \"width\": 80, \"height\": 24}\n",
+            "[0.0, \"o\", \"a\\u001b[1\"]\n",
+            "[0.2, \"i\", \"q\"]\n",
+            "\n",
+            "[0.5, \"o\", \"mb\\n\"]\n",
+        );
+        let clock = Clock::simulated();
+        let cfg = Config::builder().seed(1).build().unwrap();
+        let mut printer = Printer::new(cfg, ColorMode::TrueColor).with_clock(clock.clone());
+        let mut cast = CastWriter::new(Vec::new(), 80, 24, clock).unwrap();
+        play_cast(recording.as_bytes(), &mut [(&mut printer, &mut cast)], 2.0).unwrap();
+        printer.finalize(&mut cast).unwrap();
+        let text = String::from_utf8(cast.finish().unwrap()).unwrap();
+        let events: Vec<&str> = text.lines().skip(1).collect();
+        assert_eq!(events.len(), 3, "{text}");
+        assert!(events[0].starts_with("[0.000000, \"o\", \"\\u001b[38;2;"));
+        assert!(events[0].ends_with("a\\u001b[39m\\u001b[1\"]"), "{text}");
+        assert!(events[1].starts_with("[0.250000, \"o\", \"m\\u001b[38;2;"));
+        assert_eq!(events[2], "[0.250000, \"o\", \"\\u001b[0m\"]");
+        assert!(!text.contains('q'));
+    }
+
+    #[test]
+    fn playback_rejects_other_files() {
+        let play = |recording: &str| {
+            let mut printer =
+                Printer::new(Config::default(), ColorMode::Ansi256).with_clock(Clock::simulated());
+            match play_cast(
+                recording.as_bytes(),
+                &mut [(&mut printer, &mut Vec::new())],
+                1.0,
+            ) {
+                Err(StreamError::Io(err)) => err.to_string(),
+                other => panic!("unexpected {other:?}"),
+            }
+        };
+        assert_eq!(
+            play("{\"version\": 1}\n"),
+            "line 1: not an asciicast v2 recording"
+        );
+        assert_eq!(
+            play("{\"version\": 2}\n[1, \"o\"]\n"),
+            "line 2: expected [time, type, data]"
+        );
+        assert!(play("{\"version\": 2}\n[1, \"o\", x]\n").starts_with("line 2: "));
+        for time in ["1e30", "1e309"] {
+            assert_eq!(
+                play(&format!("{{\"version\": 2}}\n[{time}, \"o\", \"x\"]\n")),
+                "line 2: timestamp out of range"
+            );
+        }
+    }
+}
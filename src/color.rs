@@ -0,0 +1,462 @@
+use std::{
+    str::FromStr,
+    sync::{
+        OnceLock,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+/// Escape-sequence flavour used for every colored glyph.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    /// 24-bit `38;2;r;g;b`.
+    TrueColor,
+    /// xterm 256-color palette, `38;5;n`.
+    Ansi256,
+    /// SGR 30–37 and 90–97.
+    Ansi16,
+    /// SGR 30–37 only.
+    Ansi8,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "24bit" | "truecolor" => Ok(ColorMode::TrueColor),
+            "256" => Ok(ColorMode::Ansi256),
+            "16" => Ok(ColorMode::Ansi16),
+            "8" => Ok(ColorMode::Ansi8),
+            _ => Err(format!(
+                "invalid value for --colors: '{value}' (expected 8, 16, 256 or 24bit)"
+            )),
+        }
+    }
+}
+
+/// Terminals that predate the 256-color extension. `xterm-color` is not
+/// among them: like plain `xterm`, it names terminals that do 256 colors.
+pub(crate) fn limited_palette_for(term: &str) -> Option<ColorMode> {
+    if term.contains("256color") {
+        return None;
+    }
+    match term {
+        "linux" | "cygwin" | "xterm-16color" | "rxvt-16color" | "screen-16color" => {
+            Some(ColorMode::Ansi16)
+        }
+        "ansi" | "sun-color" => Some(ColorMode::Ansi8),
+        _ => None,
+    }
+}
+
+pub(crate) fn detects_truecolor_from(term: Option<&str>) -> bool {
+    term.map(|value| {
+        let lower = value.to_ascii_lowercase();
+        lower.contains("truecolor") || lower.contains("24bit")
+    })
+    .unwrap_or(false)
+}
+
+/// How RGB colors are fitted onto the xterm 256-color palette.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Quantize {
+    /// Per-channel truncation onto the 6x6x6 cube; the classic lolcat mapping.
+    Fast,
+    /// Perceptually nearest entry of the cube or the gray ramp.
+    #[default]
+    Accurate,
+}
+
+impl FromStr for Quantize {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "fast" => Ok(Quantize::Fast),
+            "accurate" => Ok(Quantize::Accurate),
+            _ => Err(format!(
+                "invalid value for --quantize: '{value}' (expected fast or accurate)"
+            )),
+        }
+    }
+}
+
+impl Quantize {
+    pub fn ansi256(self, r: u8, g: u8, b: u8) -> u8 {
+        match self {
+            Quantize::Fast => rgb_to_ansi256_fast(r, g, b),
+            Quantize::Accurate => rgb_to_ansi256(r, g, b),
+        }
+    }
+}
+
+/// Nearest xterm 256-color palette entry by perceptual distance, searching
+/// the 6x6x6 cube and the 232–255 gray ramp. Entries 0–15 are skipped since
+/// terminals restyle them.
+pub fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let table = ANSI256_TABLE.get_or_init(|| (0..LUT_CELLS).map(|_| AtomicU8::new(0)).collect());
+    let cell = (r as usize >> LUT_SHIFT) << (2 * LUT_BITS)
+        | (g as usize >> LUT_SHIFT) << LUT_BITS
+        | (b as usize >> LUT_SHIFT);
+    let entry = match table[cell].load(Ordering::Relaxed) {
+        CELL_UNKNOWN => {
+            let entry = classify_cell(r >> LUT_SHIFT, g >> LUT_SHIFT, b >> LUT_SHIFT);
+            table[cell].store(entry, Ordering::Relaxed);
+            entry
+        }
+        entry => entry,
+    };
+    match entry {
+        CELL_MIXED => nearest_ansi256(r, g, b),
+        idx => idx,
+    }
+}
+
+const LUT_BITS: usize = 6;
+const LUT_SHIFT: usize = 8 - LUT_BITS;
+const LUT_CELLS: usize = 1 << (3 * LUT_BITS);
+// Palette results start at 16, leaving the low values free as markers.
+const CELL_UNKNOWN: u8 = 0;
+const CELL_MIXED: u8 = 1;
+
+/// One entry per 4x4x4 block of RGB, filled on first use: the palette index
+/// when all eight corners of the block agree, or `CELL_MIXED` where the block
+/// straddles a boundary and each color is searched on its own.
+static ANSI256_TABLE: OnceLock<Box<[AtomicU8]>> = OnceLock::new();
+
+fn classify_cell(r: u8, g: u8, b: u8) -> u8 {
+    let coord = |step: u8, far: u8| ((step as u16 + far as u16) << LUT_SHIFT).min(255) as u8;
+    let corner = |bits: u8| {
+        nearest_ansi256(
+            coord(r, bits >> 2),
+            coord(g, bits >> 1 & 1),
+            coord(b, bits & 1),
+        )
+    };
+    let first = corner(0);
+    if (1..8).all(|bits| corner(bits) == first) {
+        first
+    } else {
+        CELL_MIXED
+    }
+}
+
+/// Perceptually nearest entry among a handful of candidates: the nearest
+/// cube level per channel (plus red's runner-up, whose weight shifts with the
+/// candidate) and the grays around the color's weighted lightness.
+fn nearest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let color = (r, g, b);
+    let (ri, gi, bi) = (
+        CUBE_NEAREST[r as usize],
+        CUBE_NEAREST[g as usize],
+        CUBE_NEAREST[b as usize],
+    );
+    let r_alt = if r > CUBE_LEVELS[ri as usize] {
+        (ri + 1).min(5)
+    } else {
+        ri.saturating_sub(1)
+    };
+    let luma = (2 * r as u16 + 4 * g as u16 + 2 * b as u16) / 8;
+    let gray = GRAY_NEAREST[luma as usize];
+    let mut best = 16 + 36 * ri + 6 * gi + bi;
+    let mut best_distance = redmean_distance(color, ANSI256_RGB[best as usize - 16]);
+    for idx in [
+        16 + 36 * r_alt + 6 * gi + bi,
+        232 + gray.saturating_sub(1),
+        232 + gray,
+        232 + (gray + 1).min(23),
+    ] {
+        let distance = redmean_distance(color, ANSI256_RGB[idx as usize - 16]);
+        if distance < best_distance {
+            best = idx;
+            best_distance = distance;
+        }
+    }
+    best
+}
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Nearest cube level index for every channel value.
+static CUBE_NEAREST: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut value = 0;
+    while value < 256 {
+        let mut idx = 0;
+        while idx < 5 && 2 * value as u16 > CUBE_LEVELS[idx] as u16 + CUBE_LEVELS[idx + 1] as u16 {
+            idx += 1;
+        }
+        table[value] = idx as u8;
+        value += 1;
+    }
+    table
+};
+
+/// Nearest gray ramp step (`8 + 10 * n`) for every channel value.
+static GRAY_NEAREST: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut value = 0;
+    while value < 256 {
+        let step = if value < 8 { 0 } else { (value - 8 + 5) / 10 };
+        table[value] = if step > 23 { 23 } else { step as u8 };
+        value += 1;
+    }
+    table
+};
+
+/// RGB of palette entries 16–255.
+static ANSI256_RGB: [(u8, u8, u8); 240] = {
+    let mut table = [(0u8, 0u8, 0u8); 240];
+    let mut idx = 0;
+    while idx < 216 {
+        table[idx] = (
+            CUBE_LEVELS[idx / 36],
+            CUBE_LEVELS[idx / 6 % 6],
+            CUBE_LEVELS[idx % 6],
+        );
+        idx += 1;
+    }
+    while idx < 240 {
+        let level = 8 + 10 * (idx - 216) as u8;
+        table[idx] = (level, level, level);
+        idx += 1;
+    }
+    table
+};
+
+/// The "redmean" weighted RGB distance, a cheap approximation of perceived
+/// difference that leans on green and adapts red/blue weights to the hue.
+fn redmean_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let mean = (a.0 as i32 + b.0 as i32) / 2;
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    ((((512 + mean) * dr * dr) >> 8) + 4 * dg * dg + (((767 - mean) * db * db) >> 8)) as u32
+}
+
+/// Truncates each channel onto the color cube, and exact grays onto the ramp.
+pub fn rgb_to_ansi256_fast(r: u8, g: u8, b: u8) -> u8 {
+    if r == g && g == b {
+        if r < 8 {
+            16
+        } else if r > 248 {
+            231
+        } else {
+            ((r as u16 - 8) * 24 / 247) as u8 + 232
+        }
+    } else {
+        let r = (r as u16 * 5 / 255) as u8;
+        let g = (g as u16 * 5 / 255) as u8;
+        let b = (b as u16 * 5 / 255) as u8;
+        16 + 36 * r + 6 * g + b
+    }
+}
+
+/// The xterm defaults for the 16 basic colors.
+const ANSI16_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Nearest of the 16 basic colors, as an index into `0..16`.
+pub fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> u8 {
+    nearest_basic(&ANSI16_PALETTE, r, g, b)
+}
+
+/// Nearest of the 8 basic colors, as an index into `0..8`.
+pub fn rgb_to_ansi8(r: u8, g: u8, b: u8) -> u8 {
+    nearest_basic(&ANSI16_PALETTE[..8], r, g, b)
+}
+
+fn nearest_basic(palette: &[(u8, u8, u8)], r: u8, g: u8, b: u8) -> u8 {
+    let distance = |&(pr, pg, pb): &(u8, u8, u8)| {
+        let dr = pr as i32 - r as i32;
+        let dg = pg as i32 - g as i32;
+        let db = pb as i32 - b as i32;
+        dr * dr + dg * dg + db * db
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, rgb)| distance(rgb))
+        .map(|(idx, _)| idx as u8)
+        .unwrap_or(0)
+}
+
+pub(crate) fn build_truecolor_prefix(buf: &mut [u8], invert: bool, r: u8, g: u8, b: u8) -> usize {
+    let mut len = 0;
+    buf[len] = 0x1b;
+    len += 1;
+    buf[len] = b'[';
+    len += 1;
+    buf[len] = if invert { b'4' } else { b'3' };
+    len += 1;
+    buf[len] = b'8';
+    len += 1;
+    buf[len] = b';';
+    len += 1;
+    buf[len] = b'2';
+    len += 1;
+    buf[len] = b';';
+    len += 1;
+    len += append_decimal_u8(&mut buf[len..], r);
+    buf[len] = b';';
+    len += 1;
+    len += append_decimal_u8(&mut buf[len..], g);
+    buf[len] = b';';
+    len += 1;
+    len += append_decimal_u8(&mut buf[len..], b);
+    buf[len] = b'm';
+    len + 1
+}
+
+pub(crate) fn build_ansi_prefix(buf: &mut [u8], invert: bool, idx: u8) -> usize {
+    let mut len = 0;
+    buf[len] = 0x1b;
+    len += 1;
+    buf[len] = b'[';
+    len += 1;
+    buf[len] = if invert { b'4' } else { b'3' };
+    len += 1;
+    buf[len] = b'8';
+    len += 1;
+    buf[len] = b';';
+    len += 1;
+    buf[len] = b'5';
+    len += 1;
+    buf[len] = b';';
+    len += 1;
+    len += append_decimal_u8(&mut buf[len..], idx);
+    buf[len] = b'm';
+    len + 1
+}
+
+pub(crate) fn build_ansi16_prefix(buf: &mut [u8], invert: bool, idx: u8) -> usize {
+    let code = match (invert, idx < 8) {
+        (false, true) => 30 + idx,
+        (false, false) => 90 + idx - 8,
+        (true, true) => 40 + idx,
+        (true, false) => 100 + idx - 8,
+    };
+    let mut len = 0;
+    buf[len] = 0x1b;
+    len += 1;
+    buf[len] = b'[';
+    len += 1;
+    len += append_decimal_u8(&mut buf[len..], code);
+    buf[len] = b'm';
+    len + 1
+}
+
+fn append_decimal_u8(dst: &mut [u8], value: u8) -> usize {
+    debug_assert!(dst.len() >= 3);
+    let hundreds = value / 100;
+    let tens = (value % 100) / 10;
+    let ones = value % 10;
+    let mut len = 0;
+    if hundreds != 0 {
+        dst[len] = b'0' + hundreds;
+        len += 1;
+        dst[len] = b'0' + tens;
+        len += 1;
+        dst[len] = b'0' + ones;
+        len += 1;
+    } else if tens != 0 {
+        dst[len] = b'0' + tens;
+        len += 1;
+        dst[len] = b'0' + ones;
+        len += 1;
+    } else {
+        dst[len] = b'0' + ones;
+        len += 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_palettes_pick_nearest_color() {
+        assert_eq!(rgb_to_ansi16(250, 10, 10), 9);
+        assert_eq!(rgb_to_ansi16(190, 20, 10), 1);
+        assert_eq!(rgb_to_ansi16(250, 250, 250), 15);
+        assert_eq!(rgb_to_ansi8(250, 10, 10), 1);
+        assert_eq!(rgb_to_ansi8(240, 240, 240), 7);
+    }
+
+    #[test]
+    fn ansi16_prefix_uses_bright_codes() {
+        let mut buf = [0u8; 16];
+        let len = build_ansi16_prefix(&mut buf, false, 3);
+        assert_eq!(&buf[..len], b"\x1b[33m");
+        let len = build_ansi16_prefix(&mut buf, false, 12);
+        assert_eq!(&buf[..len], b"\x1b[94m");
+        let len = build_ansi16_prefix(&mut buf, true, 9);
+        assert_eq!(&buf[..len], b"\x1b[101m");
+    }
+
+    #[test]
+    fn detects_truecolor_env_toggle() {
+        assert!(detects_truecolor_from(Some("truecolor")));
+        assert!(detects_truecolor_from(Some("24BIT")));
+        assert!(!detects_truecolor_from(Some("ansi")));
+        assert!(!detects_truecolor_from(None));
+    }
+
+    #[test]
+    fn rgb_to_ansi256_maps_primary_colors() {
+        assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
+        assert_eq!(rgb_to_ansi256(0, 255, 0), 46);
+        assert_eq!(rgb_to_ansi256(0, 0, 255), 21);
+        assert_eq!(rgb_to_ansi256(128, 128, 128), 244);
+    }
+
+    #[test]
+    fn accurate_quantization_prefers_grays_and_rounds() {
+        // Near-gray colors land on the ramp rather than a tinted cube entry.
+        assert_eq!(rgb_to_ansi256(100, 102, 98), 241);
+        assert_eq!(rgb_to_ansi256_fast(100, 102, 98), 65);
+        // Truncation drops 200 to the 175 level; 215 is nearer.
+        assert_eq!(rgb_to_ansi256(200, 0, 0), 160);
+        assert_eq!(rgb_to_ansi256_fast(200, 0, 0), 124);
+        assert_eq!(Quantize::Fast.ansi256(128, 128, 128), 243);
+    }
+
+    #[test]
+    fn table_and_candidates_match_brute_force() {
+        // A stride of 1 covers every color; 7 keeps the debug build quick.
+        let brute = |color| {
+            (16..=255u8)
+                .min_by_key(|&idx| redmean_distance(color, ANSI256_RGB[idx as usize - 16]))
+                .unwrap()
+        };
+        let dist = |color, idx: u8| redmean_distance(color, ANSI256_RGB[idx as usize - 16]);
+        for r in (0..=255u8).step_by(7) {
+            for g in (0..=255u8).step_by(7) {
+                for b in (0..=255u8).step_by(7) {
+                    let color = (r, g, b);
+                    let cached = rgb_to_ansi256(r, g, b);
+                    assert_eq!(cached, nearest_ansi256(r, g, b), "{color:?}");
+                    assert_eq!(dist(color, cached), dist(color, brute(color)), "{color:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::{f64::consts::FRAC_PI_2, str::FromStr};
+
+use crate::rainbow::{Background, RainbowState};
+
+// OKLCH places pure sRGB red at roughly 29 degrees rather than 0.
+const OKLCH_RED_HUE: f64 = 29.2;
+
+/// Color space the rainbow hue sweep runs through.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// The classic lolcat three-phase sine wave.
+    #[default]
+    Sine,
+    /// HSL hue wheel at fixed saturation and lightness.
+    Hsl,
+    /// OKLCH hue wheel at fixed perceptual lightness and chroma.
+    Oklch,
+}
+
+impl FromStr for ColorSpace {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "sine" => Ok(ColorSpace::Sine),
+            "hsl" => Ok(ColorSpace::Hsl),
+            "oklch" => Ok(ColorSpace::Oklch),
+            _ => Err(format!(
+                "invalid value for --colorspace: '{value}' (expected sine, hsl or oklch)"
+            )),
+        }
+    }
+}
+
+impl ColorSpace {
+    pub(crate) fn default_lightness(self, background: Background) -> f64 {
+        match (self, background) {
+            (ColorSpace::Sine | ColorSpace::Hsl, Background::Dark) => 0.5,
+            (ColorSpace::Sine | ColorSpace::Hsl, Background::Light) => 0.4,
+            (ColorSpace::Oklch, Background::Dark) => 0.75,
+            (ColorSpace::Oklch, Background::Light) => 0.6,
+        }
+    }
+
+    pub(crate) fn default_chroma(self) -> f64 {
+        match self {
+            ColorSpace::Sine | ColorSpace::Hsl => 1.0,
+            ColorSpace::Oklch => 0.13,
+        }
+    }
+
+    /// Color at rainbow phase `angle`. Hues line up with the sine rainbow, so
+    /// switching spaces keeps red, green and blue where they were.
+    pub(crate) fn channels(self, angle: f64, lightness: f64, chroma: f64) -> (u8, u8, u8) {
+        let hue = (FRAC_PI_2 - angle).to_degrees().rem_euclid(360.0);
+        match self {
+            ColorSpace::Sine => RainbowState::from_angle(angle).channels(Background::Dark),
+            ColorSpace::Hsl => hsl_to_rgb(hue, chroma, lightness),
+            ColorSpace::Oklch => oklch_to_rgb(lightness, chroma, hue + OKLCH_RED_HUE),
+        }
+    }
+}
+
+/// `hue` in degrees, `saturation` and `lightness` in `0..=1`.
+pub(crate) fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
+    let c = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = lightness - c / 2.0;
+    (to_byte(r + m), to_byte(g + m), to_byte(b + m))
+}
+
+/// OKLCH to sRGB. Colors outside the sRGB gamut keep their lightness and hue
+/// and lose chroma until they fit, instead of being clipped per channel.
+pub(crate) fn oklch_to_rgb(lightness: f64, chroma: f64, hue: f64) -> (u8, u8, u8) {
+    let (sin, cos) = hue.to_radians().sin_cos();
+    let linear = |c: f64| oklab_to_linear_srgb(lightness, c * cos, c * sin);
+    let mut rgb = linear(chroma);
+    if !in_gamut(rgb) {
+        let (mut lo, mut hi) = (0.0, chroma);
+        for _ in 0..16 {
+            let mid = (lo + hi) / 2.0;
+            if in_gamut(linear(mid)) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        rgb = linear(lo);
+    }
+    (
+        to_byte(linear_to_srgb(rgb.0)),
+        to_byte(linear_to_srgb(rgb.1)),
+        to_byte(linear_to_srgb(rgb.2)),
+    )
+}
+
+fn oklab_to_linear_srgb(l: f64, a: f64, b: f64) -> (f64, f64, f64) {
+    let l_ = l + 0.396_337_777_4 * a + 0.215_803_757_3 * b;
+    let m_ = l - 0.105_561_345_8 * a - 0.063_854_172_8 * b;
+    let s_ = l - 0.089_484_177_5 * a - 1.291_485_548_0 * b;
+    let (l3, m3, s3) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+    (
+        4.076_741_662_1 * l3 - 3.307_711_591_3 * m3 + 0.230_969_929_2 * s3,
+        -1.268_438_004_6 * l3 + 2.609_757_401_1 * m3 - 0.341_319_396_5 * s3,
+        -0.004_196_086_3 * l3 - 0.703_418_614_7 * m3 + 1.707_614_701_0 * s3,
+    )
+}
+
+fn in_gamut((r, g, b): (f64, f64, f64)) -> bool {
+    const EPS: f64 = 1e-4;
+    [r, g, b].iter().all(|c| (-EPS..=1.0 + EPS).contains(c))
+}
+
+fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.003_130_8 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_byte(value: f64) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hsl_primaries() {
+        assert_eq!(hsl_to_rgb(0.0, 1.0, 0.5), (255, 0, 0));
+        assert_eq!(hsl_to_rgb(120.0, 1.0, 0.5), (0, 255, 0));
+        assert_eq!(hsl_to_rgb(240.0, 1.0, 0.5), (0, 0, 255));
+        assert_eq!(hsl_to_rgb(42.0, 0.0, 1.0), (255, 255, 255));
+    }
+
+    #[test]
+    fn oklch_extremes_and_gamut_mapping() {
+        assert_eq!(oklch_to_rgb(1.0, 0.0, 0.0), (255, 255, 255));
+        assert_eq!(oklch_to_rgb(0.0, 0.0, 0.0), (0, 0, 0));
+        // Far outside sRGB: still a valid, reddish color rather than a clipped mess.
+        let (r, g, b) = oklch_to_rgb(0.7, 0.4, OKLCH_RED_HUE);
+        assert!(r > g && r > b, "unexpected {:?}", (r, g, b));
+    }
+
+    #[test]
+    fn hue_sweeps_line_up_with_the_sine_rainbow() {
+        // The sine rainbow peaks in red at a quarter turn.
+        let angle = FRAC_PI_2;
+        for space in [ColorSpace::Sine, ColorSpace::Hsl, ColorSpace::Oklch] {
+            let (r, g, b) = space.channels(
+                angle,
+                space.default_lightness(Background::Dark),
+                space.default_chroma(),
+            );
+            assert!(r > g && r > b, "{space:?} gave {:?}", (r, g, b));
+        }
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!("oklch".parse::<ColorSpace>(), Ok(ColorSpace::Oklch));
+        assert!("lab".parse::<ColorSpace>().is_err());
+    }
+}
@@ -0,0 +1,474 @@
+use crate::{
+    Background, ColorMode, ColorSpace, Direction, Effect, ExistingColor, Gradient, GradientMode,
+    Granularity, LinkStyle, Quantize, TabStops,
+};
+
+/// Rendering options shared by [`Printer`](crate::Printer) and the `lolcat` binary.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub(crate) spread: f64,
+    pub(crate) freq: f64,
+    pub(crate) seed: u64,
+    pub(crate) animate: bool,
+    pub(crate) animate_block: bool,
+    pub(crate) forever: bool,
+    pub(crate) effect: Effect,
+    pub(crate) block_lines: Option<usize>,
+    pub(crate) duration: u32,
+    pub(crate) speed: f64,
+    pub(crate) invert: bool,
+    pub(crate) colors: Option<ColorMode>,
+    pub(crate) gradient: Option<Gradient>,
+    pub(crate) gradient_mode: GradientMode,
+    pub(crate) colorspace: ColorSpace,
+    pub(crate) lightness: Option<f64>,
+    pub(crate) chroma: Option<f64>,
+    pub(crate) background: Option<Background>,
+    pub(crate) quantize: Quantize,
+    pub(crate) dither: bool,
+    pub(crate) direction: Direction,
+    pub(crate) center: Option<(f64, f64)>,
+    pub(crate) granularity: Granularity,
+    pub(crate) tabs: TabStops,
+    pub(crate) keep_tabs: bool,
+    pub(crate) existing_color: ExistingColor,
+    pub(crate) link_style: LinkStyle,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            spread: 3.0,
+            freq: 0.1,
+            seed: 0,
+            animate: false,
+            animate_block: false,
+            forever: false,
+            effect: Effect::default(),
+            block_lines: None,
+            duration: 12,
+            speed: 20.0,
+            invert: false,
+            colors: None,
+            gradient: None,
+            gradient_mode: GradientMode::default(),
+            colorspace: ColorSpace::default(),
+            lightness: None,
+            chroma: None,
+            background: None,
+            quantize: Quantize::default(),
+            dither: false,
+            direction: Direction::default(),
+            center: None,
+            granularity: Granularity::default(),
+            tabs: TabStops::default(),
+            keep_tabs: false,
+            existing_color: ExistingColor::default(),
+            link_style: LinkStyle::default(),
+        }
+    }
+}
+
+impl Config {
+    /// A builder starting from the defaults.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// A builder starting from this config, for adjusting it after detection.
+    pub fn to_builder(&self) -> ConfigBuilder {
+        ConfigBuilder {
+            config: self.clone(),
+        }
+    }
+
+    /// How many characters one hue step spans.
+    pub fn spread(&self) -> f64 {
+        self.spread
+    }
+
+    /// How fast the hue moves along the text.
+    pub fn freq(&self) -> f64 {
+        self.freq
+    }
+
+    /// Starting phase seed; 0 means random.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether lines are animated.
+    pub fn animate(&self) -> bool {
+        self.animate
+    }
+
+    /// Whether animation runs over whole blocks of lines at once.
+    pub fn animate_block(&self) -> bool {
+        self.animate_block
+    }
+
+    /// Whether animation loops until stopped instead of running `duration`
+    /// frames.
+    pub fn forever(&self) -> bool {
+        self.forever
+    }
+
+    /// What each animation frame does to the text.
+    pub fn effect(&self) -> Effect {
+        self.effect
+    }
+
+    /// Most lines animated together; `None` takes the whole input.
+    pub fn block_lines(&self) -> Option<usize> {
+        self.block_lines
+    }
+
+    /// Animation frames per line.
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+
+    /// Animation frames per second.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Whether the background is colored instead of the foreground.
+    pub fn invert(&self) -> bool {
+        self.invert
+    }
+
+    /// Whether 24-bit color was requested.
+    pub fn truecolor(&self) -> bool {
+        self.colors == Some(ColorMode::TrueColor)
+    }
+
+    /// Explicitly requested color mode; `None` leaves it to detection.
+    pub fn colors(&self) -> Option<ColorMode> {
+        self.colors
+    }
+
+    /// Custom color stops replacing the rainbow, if any.
+    pub fn gradient(&self) -> Option<&Gradient> {
+        self.gradient.as_ref()
+    }
+
+    /// How the gradient repeats along the phase.
+    pub fn gradient_mode(&self) -> GradientMode {
+        self.gradient_mode
+    }
+
+    /// Color space of the hue sweep.
+    pub fn colorspace(&self) -> ColorSpace {
+        self.colorspace
+    }
+
+    /// Explicit terminal background; `None` assumes a dark one.
+    pub fn background(&self) -> Option<Background> {
+        self.background
+    }
+
+    /// How colors are fitted onto the 256-color palette.
+    pub fn quantize(&self) -> Quantize {
+        self.quantize
+    }
+
+    /// Whether reduced palettes are dithered.
+    pub fn dither(&self) -> bool {
+        self.dither
+    }
+
+    /// Which way the rainbow runs.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Radial center as `(column, row)`; `None` until set or measured.
+    pub fn center(&self) -> Option<(f64, f64)> {
+        self.center
+    }
+
+    /// How far one color reaches.
+    pub fn granularity(&self) -> Granularity {
+        self.granularity
+    }
+
+    /// Tab stops that tabs expand to.
+    pub fn tabs(&self) -> &TabStops {
+        &self.tabs
+    }
+
+    /// Whether tabs are written as-is instead of as colored spaces.
+    pub fn keep_tabs(&self) -> bool {
+        self.keep_tabs
+    }
+
+    /// What happens to colors already in the input.
+    pub fn existing_color(&self) -> ExistingColor {
+        self.existing_color
+    }
+
+    /// How hyperlink text is colored.
+    pub fn link_style(&self) -> LinkStyle {
+        self.link_style
+    }
+
+    /// Lightness of the hue sweep, falling back to the color space default
+    /// for the background.
+    pub fn lightness(&self) -> f64 {
+        self.lightness.unwrap_or_else(|| {
+            self.colorspace
+                .default_lightness(self.background.unwrap_or_default())
+        })
+    }
+
+    /// Chroma (saturation for HSL), falling back to the color space default.
+    pub fn chroma(&self) -> f64 {
+        self.chroma
+            .unwrap_or_else(|| self.colorspace.default_chroma())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.spread < 0.1 {
+            return Err("--spread must be >= 0.1".to_string());
+        }
+        if self.speed < 0.1 {
+            return Err("--speed must be >= 0.1".to_string());
+        }
+        if self.duration == 0 {
+            return Err("--duration must be >= 1".to_string());
+        }
+        if self.block_lines == Some(0) {
+            return Err("--animate-block must be >= 1 line".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.lightness()) {
+            return Err("--lightness must be between 0 and 1".to_string());
+        }
+        let max_chroma = match self.colorspace {
+            ColorSpace::Oklch => 0.4,
+            ColorSpace::Sine | ColorSpace::Hsl => 1.0,
+        };
+        if !(0.0..=max_chroma).contains(&self.chroma()) {
+            return Err(format!("--chroma must be between 0 and {max_chroma}"));
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`Config`]; each setter mirrors one of the `lolcat` flags.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Rainbow spread, `--spread`.
+    pub fn spread(&mut self, spread: f64) -> &mut Self {
+        self.config.spread = spread;
+        self
+    }
+
+    /// Rainbow frequency, `--freq`.
+    pub fn freq(&mut self, freq: f64) -> &mut Self {
+        self.config.freq = freq;
+        self
+    }
+
+    /// Rainbow seed, `--seed`; 0 picks a random starting phase.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.config.seed = seed;
+        self
+    }
+
+    /// Animate each line, `--animate`.
+    pub fn animate(&mut self, animate: bool) -> &mut Self {
+        self.config.animate = animate;
+        self
+    }
+
+    /// Animate the input block by block instead of line by line,
+    /// `--animate-block`.
+    pub fn animate_block(&mut self, animate_block: bool) -> &mut Self {
+        self.config.animate_block = animate_block;
+        self
+    }
+
+    /// Keep animating until [`Printer::with_stop`](crate::Printer::with_stop)
+    /// says otherwise, `--animate=forever`.
+    pub fn forever(&mut self, forever: bool) -> &mut Self {
+        self.config.forever = forever;
+        self
+    }
+
+    /// What each animation frame does to the text, `--effect`.
+    pub fn effect(&mut self, effect: Effect) -> &mut Self {
+        self.config.effect = effect;
+        self
+    }
+
+    /// Cap on the lines in one animated block, `--animate-block=N`.
+    pub fn block_lines(&mut self, lines: Option<usize>) -> &mut Self {
+        self.config.block_lines = lines;
+        self
+    }
+
+    /// Number of animation frames per line, `--duration`.
+    pub fn duration(&mut self, frames: u32) -> &mut Self {
+        self.config.duration = frames;
+        self
+    }
+
+    /// Animation frames per second, `--speed`.
+    pub fn speed(&mut self, speed: f64) -> &mut Self {
+        self.config.speed = speed;
+        self
+    }
+
+    /// Color the background instead of the foreground, `--invert`.
+    pub fn invert(&mut self, invert: bool) -> &mut Self {
+        self.config.invert = invert;
+        self
+    }
+
+    /// Force 24-bit output regardless of `COLORTERM`, `--truecolor`.
+    pub fn truecolor(&mut self, truecolor: bool) -> &mut Self {
+        if truecolor {
+            self.config.colors = Some(ColorMode::TrueColor);
+        } else if self.config.truecolor() {
+            self.config.colors = None;
+        }
+        self
+    }
+
+    /// Force a color mode, or `None` to detect it, `--colors`.
+    pub fn colors(&mut self, mode: Option<ColorMode>) -> &mut Self {
+        self.config.colors = mode;
+        self
+    }
+
+    /// Replace the rainbow with custom color stops, `--gradient`.
+    pub fn gradient(&mut self, gradient: Gradient) -> &mut Self {
+        self.config.gradient = Some(gradient);
+        self
+    }
+
+    /// How the gradient repeats along the phase, `--gradient-mode`.
+    pub fn gradient_mode(&mut self, mode: GradientMode) -> &mut Self {
+        self.config.gradient_mode = mode;
+        self
+    }
+
+    /// Color space of the rainbow hue sweep, `--colorspace`.
+    pub fn colorspace(&mut self, colorspace: ColorSpace) -> &mut Self {
+        self.config.colorspace = colorspace;
+        self
+    }
+
+    /// Lightness in `0..=1` for the HSL and OKLCH sweeps, `--lightness`.
+    pub fn lightness(&mut self, lightness: f64) -> &mut Self {
+        self.config.lightness = Some(lightness);
+        self
+    }
+
+    /// OKLCH chroma (`0..=0.4`) or HSL saturation (`0..=1`), `--chroma`.
+    pub fn chroma(&mut self, chroma: f64) -> &mut Self {
+        self.config.chroma = Some(chroma);
+        self
+    }
+
+    /// Tune colors for a dark or light terminal background, `--background`.
+    pub fn background(&mut self, background: Background) -> &mut Self {
+        self.config.background = Some(background);
+        self
+    }
+
+    /// How colors are fitted onto the 256-color palette, `--quantize`.
+    pub fn quantize(&mut self, quantize: Quantize) -> &mut Self {
+        self.config.quantize = quantize;
+        self
+    }
+
+    /// Ordered dithering when reducing to 256, 16 or 8 colors, `--dither`.
+    pub fn dither(&mut self, dither: bool) -> &mut Self {
+        self.config.dither = dither;
+        self
+    }
+
+    /// Which way the rainbow runs, `--direction`.
+    pub fn direction(&mut self, direction: Direction) -> &mut Self {
+        self.config.direction = direction;
+        self
+    }
+
+    /// Center `(column, row)` for radial gradients, `--center`.
+    pub fn center(&mut self, center: (f64, f64)) -> &mut Self {
+        self.config.center = Some(center);
+        self
+    }
+
+    /// Color per character, word, line or paragraph, `--granularity`.
+    pub fn granularity(&mut self, granularity: Granularity) -> &mut Self {
+        self.config.granularity = granularity;
+        self
+    }
+
+    /// Tab stops that tabs expand to, `--tabs`.
+    pub fn tabs(&mut self, tabs: TabStops) -> &mut Self {
+        self.config.tabs = tabs;
+        self
+    }
+
+    /// Pass tabs through uncolored, still advancing the hue, `--keep-tabs`.
+    pub fn keep_tabs(&mut self, keep_tabs: bool) -> &mut Self {
+        self.config.keep_tabs = keep_tabs;
+        self
+    }
+
+    /// Override, preserve or strip colors already in the input, `--existing-color`.
+    pub fn existing_color(&mut self, existing_color: ExistingColor) -> &mut Self {
+        self.config.existing_color = existing_color;
+        self
+    }
+
+    /// How OSC 8 hyperlink text is colored, `--link-style`.
+    pub fn link_style(&mut self, link_style: LinkStyle) -> &mut Self {
+        self.config.link_style = link_style;
+        self
+    }
+
+    /// Validates the settings and returns the config.
+    pub fn build(&self) -> Result<Config, String> {
+        self.config.validate()?;
+        Ok(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_defaults_match_config() {
+        let cfg = Config::builder().build().unwrap();
+        assert_eq!(cfg.spread(), 3.0);
+        assert_eq!(cfg.freq(), 0.1);
+        assert_eq!(cfg.duration(), 12);
+        assert_eq!(cfg.speed(), 20.0);
+    }
+
+    #[test]
+    fn builder_rejects_invalid_values() {
+        let err = Config::builder().spread(0.01).build().unwrap_err();
+        assert!(err.contains("spread"), "unexpected error: {err}");
+        let err = Config::builder().duration(0).build().unwrap_err();
+        assert!(err.contains("duration"), "unexpected error: {err}");
+        let err = Config::builder().block_lines(Some(0)).build().unwrap_err();
+        assert!(err.contains("--animate-block"), "unexpected error: {err}");
+        let err = Config::builder()
+            .colorspace(ColorSpace::Oklch)
+            .chroma(0.8)
+            .build()
+            .unwrap_err();
+        assert!(err.contains("chroma"), "unexpected error: {err}");
+    }
+}
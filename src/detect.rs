@@ -0,0 +1,287 @@
+use std::env;
+
+use crate::{
+    ColorMode, Config,
+    color::{detects_truecolor_from, limited_palette_for},
+    query::TerminalReport,
+    terminfo::Terminfo,
+};
+
+/// Whether to color at all and in which mode, with the rule behind each choice.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorSupport {
+    pub enabled: bool,
+    pub mode: ColorMode,
+    /// Which rule turned color on or off.
+    pub enabled_reason: String,
+    /// Which rule picked `mode`.
+    pub mode_reason: String,
+}
+
+impl ColorSupport {
+    /// Upgrades to truecolor when the terminal itself reported `RGB` or `Tc`,
+    /// unless a mode was requested explicitly.
+    pub fn apply_report(&mut self, config: &Config, report: &TerminalReport) {
+        if config.colors.is_none() && report.truecolor == Some(true) {
+            self.mode = ColorMode::TrueColor;
+            self.mode_reason = "terminal answered XTGETTCAP RGB/Tc".to_string();
+        }
+    }
+}
+
+/// Decides color output from `--force`, whether stdout is a terminal, the
+/// `NO_COLOR`, `CLICOLOR`, `CLICOLOR_FORCE`, `FORCE_COLOR`, `COLORTERM` and
+/// `TERM` conventions, and the compiled terminfo entry for `TERM`.
+pub fn detect_color_support(config: &Config, force: bool, is_tty: bool) -> ColorSupport {
+    detect_from(
+        config,
+        force,
+        is_tty,
+        &|name| env::var(name).ok(),
+        &Terminfo::load,
+    )
+}
+
+/// Picks the color mode alone, for output that is colored regardless.
+pub fn choose_color_mode(config: &Config) -> ColorMode {
+    detect_mode(config, &|name| env::var(name).ok(), &Terminfo::load).0
+}
+
+type EnvLookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+type TerminfoLookup<'a> = &'a dyn Fn(&str) -> Option<Terminfo>;
+
+fn detect_from(
+    config: &Config,
+    force: bool,
+    is_tty: bool,
+    env: EnvLookup,
+    terminfo: TerminfoLookup,
+) -> ColorSupport {
+    let (enabled, enabled_reason) = detect_enabled(force, is_tty, env);
+    let (mode, mode_reason) = detect_mode(config, env, terminfo);
+    ColorSupport {
+        enabled,
+        mode,
+        enabled_reason,
+        mode_reason,
+    }
+}
+
+fn detect_enabled(force: bool, is_tty: bool, env: EnvLookup) -> (bool, String) {
+    let var = |name: &str| env(name).filter(|value| !value.is_empty());
+    if force {
+        return (true, "--force".to_string());
+    }
+    if var("NO_COLOR").is_some() {
+        return (false, "NO_COLOR is set".to_string());
+    }
+    if let Some(value) = var("CLICOLOR_FORCE").filter(|value| value != "0") {
+        return (true, format!("CLICOLOR_FORCE={value}"));
+    }
+    if let Some(value) = env("FORCE_COLOR") {
+        let enabled = !matches!(value.as_str(), "0" | "false");
+        return (enabled, format!("FORCE_COLOR={value}"));
+    }
+    if !is_tty {
+        return (false, "stdout is not a terminal".to_string());
+    }
+    if let Some(term) =
+        var("TERM").filter(|term| matches!(term.as_str(), "dumb" | "vt100" | "vt220"))
+    {
+        return (false, format!("TERM={term}"));
+    }
+    if var("CLICOLOR").as_deref() == Some("0") {
+        return (false, "CLICOLOR=0".to_string());
+    }
+    (true, "stdout is a terminal".to_string())
+}
+
+fn detect_mode(config: &Config, env: EnvLookup, terminfo: TerminfoLookup) -> (ColorMode, String) {
+    if let Some(mode) = config.colors {
+        return (mode, "requested with --colors/--truecolor".to_string());
+    }
+    match env("FORCE_COLOR").as_deref() {
+        Some("3") => return (ColorMode::TrueColor, "FORCE_COLOR=3".to_string()),
+        Some("2") => return (ColorMode::Ansi256, "FORCE_COLOR=2".to_string()),
+        _ => {}
+    }
+    if let Some(value) = env("COLORTERM").filter(|value| detects_truecolor_from(Some(value))) {
+        return (ColorMode::TrueColor, format!("COLORTERM={value}"));
+    }
+    let Some(term) = env("TERM").filter(|term| !term.is_empty()) else {
+        return (
+            ColorMode::Ansi256,
+            "TERM is unset, assuming 256".to_string(),
+        );
+    };
+    if term.ends_with("-direct") {
+        return (ColorMode::TrueColor, format!("TERM={term}"));
+    }
+    let info = terminfo(&term);
+    if let Some(info) = &info {
+        if info.rgb {
+            return (ColorMode::TrueColor, format!("terminfo {term} has RGB/Tc"));
+        }
+        if info.colors.is_some_and(|colors| colors >= 1 << 24) {
+            return (
+                ColorMode::TrueColor,
+                format!("terminfo {term} has 24-bit colors"),
+            );
+        }
+    }
+    if term.contains("256color") {
+        return (ColorMode::Ansi256, format!("TERM={term}"));
+    }
+    if let Some(mode) = limited_palette_for(&term) {
+        return (mode, format!("TERM={term} predates 256 colors"));
+    }
+    match info.and_then(|info| info.colors) {
+        Some(colors) if colors >= 256 => (
+            ColorMode::Ansi256,
+            format!("terminfo {term} has {colors} colors"),
+        ),
+        // Entries like plain `xterm` undersell most real terminals, so a small
+        // count is not enough to downgrade.
+        Some(colors) => (
+            ColorMode::Ansi256,
+            format!("terminfo {term} claims {colors} colors, assuming 256"),
+        ),
+        None => (
+            ColorMode::Ansi256,
+            format!("no terminfo for TERM={term}, assuming 256"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(
+        vars: &[(&str, &str)],
+        force: bool,
+        is_tty: bool,
+        info: Option<Terminfo>,
+    ) -> ColorSupport {
+        let cfg = Config::default();
+        let env = |name: &str| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        detect_from(&cfg, force, is_tty, &env, &|_| info.clone())
+    }
+
+    #[test]
+    fn force_beats_no_color_and_no_color_beats_tty() {
+        let support = detect(&[("NO_COLOR", "1")], true, false, None);
+        assert!(support.enabled);
+        assert_eq!(support.enabled_reason, "--force");
+        let support = detect(&[("NO_COLOR", "1")], false, true, None);
+        assert!(!support.enabled);
+        assert!(detect(&[("NO_COLOR", "")], false, true, None).enabled);
+    }
+
+    #[test]
+    fn clicolor_and_force_color_conventions() {
+        assert!(detect(&[("CLICOLOR_FORCE", "1")], false, false, None).enabled);
+        assert!(!detect(&[("CLICOLOR_FORCE", "0")], false, false, None).enabled);
+        assert!(detect(&[("FORCE_COLOR", "1")], false, false, None).enabled);
+        assert!(!detect(&[("FORCE_COLOR", "0")], false, true, None).enabled);
+        assert!(!detect(&[("CLICOLOR", "0")], false, true, None).enabled);
+        assert!(!detect(&[("TERM", "dumb")], false, true, None).enabled);
+        assert!(!detect(&[("TERM", "vt220")], false, true, None).enabled);
+        assert!(!detect(&[], false, false, None).enabled);
+    }
+
+    #[test]
+    fn mode_rules_in_order() {
+        let mode = |vars: &[(&str, &str)], info| detect(vars, true, true, info).mode;
+        assert_eq!(mode(&[("FORCE_COLOR", "3")], None), ColorMode::TrueColor);
+        assert_eq!(
+            mode(&[("COLORTERM", "truecolor")], None),
+            ColorMode::TrueColor
+        );
+        assert_eq!(
+            mode(&[("TERM", "xterm-direct")], None),
+            ColorMode::TrueColor
+        );
+        assert_eq!(
+            mode(&[("TERM", "xterm-256color")], None),
+            ColorMode::Ansi256
+        );
+        assert_eq!(mode(&[("TERM", "linux")], None), ColorMode::Ansi16);
+        assert_eq!(mode(&[("TERM", "ansi")], None), ColorMode::Ansi8);
+        let rgb = Terminfo {
+            colors: Some(256),
+            rgb: true,
+        };
+        assert_eq!(
+            mode(&[("TERM", "tmux-256color")], Some(rgb)),
+            ColorMode::TrueColor
+        );
+        let eight = Terminfo {
+            colors: Some(8),
+            rgb: false,
+        };
+        // xterm variants all run on terminals that do 256 colors.
+        for term in ["xterm", "xterm-color"] {
+            let support = detect(&[("TERM", term)], true, true, Some(eight.clone()));
+            assert_eq!(support.mode, ColorMode::Ansi256);
+            assert!(support.mode_reason.contains("claims 8"));
+        }
+    }
+
+    #[test]
+    fn terminal_report_upgrades_detected_mode_only() {
+        let report = TerminalReport {
+            truecolor: Some(true),
+            background: None,
+        };
+        let mut support = detect(&[("TERM", "xterm")], true, true, None);
+        support.apply_report(&Config::default(), &report);
+        assert_eq!(support.mode, ColorMode::TrueColor);
+
+        let cfg = Config::builder()
+            .colors(Some(ColorMode::Ansi256))
+            .build()
+            .unwrap();
+        let mut support = detect_from(&cfg, true, true, &|_| None, &|_| None);
+        support.apply_report(&cfg, &report);
+        assert_eq!(support.mode, ColorMode::Ansi256);
+    }
+
+    #[test]
+    fn choose_color_mode_prefers_truecolor_flag() {
+        let cfg = Config::builder().truecolor(true).build().unwrap();
+        assert_eq!(choose_color_mode(&cfg), ColorMode::TrueColor);
+        let env = |name: &str| match name {
+            "COLORTERM" => Some("ansi".to_string()),
+            "TERM" => Some("linux".to_string()),
+            _ => None,
+        };
+        assert_eq!(detect_mode(&cfg, &env, &|_| None).0, ColorMode::TrueColor);
+        let cfg = Config::default();
+        let colorterm = |value: &str| {
+            detect_mode(
+                &cfg,
+                &|name| (name == "COLORTERM").then(|| value.to_string()),
+                &|_| None,
+            )
+            .0
+        };
+        assert_eq!(colorterm("24bit"), ColorMode::TrueColor);
+        assert_eq!(colorterm("ansi"), ColorMode::Ansi256);
+    }
+
+    #[test]
+    fn explicit_mode_wins() {
+        let cfg = Config::builder()
+            .colors(Some(ColorMode::Ansi16))
+            .build()
+            .unwrap();
+        let env = |name: &str| (name == "COLORTERM").then(|| "truecolor".to_string());
+        let support = detect_from(&cfg, false, true, &env, &|_| None);
+        assert_eq!(support.mode, ColorMode::Ansi16);
+    }
+}
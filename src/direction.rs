@@ -0,0 +1,127 @@
+use std::str::FromStr;
+
+use crate::{TabStops, width::text_width};
+
+/// Which way the rainbow runs across the (column, row) grid.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Direction {
+    /// Shifts along each line and down the lines; the classic lolcat look.
+    #[default]
+    Diagonal,
+    /// Changes across columns only, every line alike.
+    Horizontal,
+    /// Changes down the lines only, each line a single color.
+    Vertical,
+    /// Runs along an angle in degrees: 0 is left to right, 90 top to bottom.
+    Angle(f64),
+    /// Rings around a center point, see [`ConfigBuilder::center`](crate::ConfigBuilder::center).
+    Radial,
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid value for --direction: '{value}' \
+                 (expected horizontal, vertical, diagonal, angle=DEG or radial)"
+            )
+        };
+        match value {
+            "diagonal" => Ok(Direction::Diagonal),
+            "horizontal" => Ok(Direction::Horizontal),
+            "vertical" => Ok(Direction::Vertical),
+            "radial" => Ok(Direction::Radial),
+            _ => {
+                let degrees = value
+                    .strip_prefix("angle=")
+                    .and_then(|deg| deg.parse::<f64>().ok())
+                    .filter(|deg| deg.is_finite())
+                    .ok_or_else(invalid)?;
+                Ok(Direction::Angle(degrees))
+            }
+        }
+    }
+}
+
+impl Direction {
+    /// Phase weights of one column (already divided by the spread) and one
+    /// row, for the directions that are linear in both.
+    pub(crate) fn weights(self) -> Option<(f64, f64)> {
+        match self {
+            Direction::Diagonal => Some((1.0, 1.0)),
+            Direction::Horizontal => Some((1.0, 0.0)),
+            Direction::Vertical => Some((0.0, 1.0)),
+            Direction::Angle(degrees) => {
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                Some((cos, sin))
+            }
+            Direction::Radial => None,
+        }
+    }
+}
+
+/// How far one color reaches before the hue moves on.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Granularity {
+    /// A new color for every character.
+    #[default]
+    Char,
+    /// One color per whitespace-separated word.
+    Word,
+    /// One color per line.
+    Line,
+    /// One color per run of lines between blank lines.
+    Paragraph,
+}
+
+impl FromStr for Granularity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "char" => Ok(Granularity::Char),
+            "word" => Ok(Granularity::Word),
+            "line" => Ok(Granularity::Line),
+            "paragraph" => Ok(Granularity::Paragraph),
+            _ => Err(format!(
+                "invalid value for --granularity: '{value}' (expected char, word, line or paragraph)"
+            )),
+        }
+    }
+}
+
+/// Middle of a block of text as `(column, row)`: half the widest line, in
+/// terminal cells with escape sequences skipped and tabs expanded to
+/// `tabs`, and half the line count.
+pub fn block_center(text: &str, tabs: &TabStops) -> (f64, f64) {
+    let (mut width, mut height) = (0usize, 0usize);
+    for line in text.lines() {
+        width = width.max(text_width(line, tabs));
+        height += 1;
+    }
+    (width as f64 / 2.0, height as f64 / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_directions() {
+        assert_eq!("radial".parse::<Direction>(), Ok(Direction::Radial));
+        assert_eq!("angle=30".parse::<Direction>(), Ok(Direction::Angle(30.0)));
+        assert!("angle=".parse::<Direction>().is_err());
+        assert!("sideways".parse::<Direction>().is_err());
+    }
+
+    #[test]
+    fn block_center_uses_widest_line() {
+        let tabs = TabStops::default();
+        assert_eq!(block_center("ab\nabcdef\n\n", &tabs), (3.0, 1.5));
+        assert_eq!(block_center("日本語\nabcd", &tabs), (3.0, 1.0));
+        assert_eq!(block_center("", &tabs), (0.0, 0.0));
+        assert_eq!(block_center("\x1b[1mab\x1b[0m\na\tb", &tabs), (4.5, 1.0));
+    }
+}
@@ -0,0 +1,247 @@
+//! Frame-by-frame looks for `--animate`. Every effect ends on the plain,
+//! unanimated rendering, so the last frame is what stays on screen.
+
+use std::{borrow::Cow, f64::consts::TAU, str::FromStr};
+
+use crate::grapheme::Segmenter;
+
+/// Cells from one wave crest to the next.
+const WAVE_LENGTH: f64 = 12.0;
+/// Frames for a crest to travel one wave length.
+const WAVE_FRAMES: f64 = 8.0;
+/// How far the wave troughs dim.
+const WAVE_DEPTH: f64 = 0.7;
+/// Breaths over the whole animation.
+const PULSES: f64 = 2.0;
+/// How far a pulse dims.
+const PULSE_DEPTH: f64 = 0.6;
+/// Share of cells that sparkle at the start.
+const SPARKLE_RATE: f64 = 0.08;
+/// How far a sparkle goes toward white.
+const SPARKLE_SHADE: f64 = 1.8;
+
+/// How `--animate` changes the output from frame to frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Effect {
+    /// The rainbow slides along the text.
+    #[default]
+    Shift,
+    /// A ripple of brightness runs across the text and settles.
+    Wave,
+    /// The whole text breathes darker and brighter.
+    Pulse,
+    /// Characters appear one after another.
+    Typewriter,
+    /// The text scrolls left through the rainbow, wrapping around.
+    Marquee,
+    /// Random cells flash white, seeded by `--seed`.
+    Sparkle,
+    /// The text brightens from black.
+    FadeIn,
+}
+
+impl FromStr for Effect {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "shift" => Ok(Effect::Shift),
+            "wave" => Ok(Effect::Wave),
+            "pulse" => Ok(Effect::Pulse),
+            "typewriter" => Ok(Effect::Typewriter),
+            "marquee" => Ok(Effect::Marquee),
+            "sparkle" => Ok(Effect::Sparkle),
+            "fade-in" => Ok(Effect::FadeIn),
+            _ => Err(format!(
+                "invalid value for --effect: '{value}' (expected shift, wave, pulse, \
+                 typewriter, marquee, sparkle or fade-in)"
+            )),
+        }
+    }
+}
+
+/// Frame `index` of `count`, and for the typewriter how many cells of the
+/// animated text come before the current line and in total.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub(crate) index: u32,
+    pub(crate) count: u32,
+    pub(crate) cells_before: usize,
+    pub(crate) total_cells: usize,
+}
+
+impl Frame {
+    /// Share of the animation done once this frame shows, reaching 1 on the
+    /// last one.
+    fn progress(&self) -> f64 {
+        (self.index + 1) as f64 / self.count as f64
+    }
+}
+
+/// What an effect makes of one cell.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Look {
+    /// Blank for now.
+    Hidden,
+    /// The color scaled toward black (0) or white (2); 1 leaves it alone.
+    Shade(f64),
+}
+
+impl Effect {
+    /// Whether frames step the rainbow offset along, as plain `--animate` does.
+    pub(crate) fn shifts_hue(self) -> bool {
+        self == Effect::Shift
+    }
+
+    /// The look of the cell at `column` of `row` in `frame`.
+    pub(crate) fn look(self, frame: &Frame, column: usize, row: f64, seed: u64) -> Look {
+        if frame.index + 1 >= frame.count {
+            return Look::Shade(1.0);
+        }
+        let progress = frame.progress();
+        match self {
+            Effect::Shift | Effect::Marquee => Look::Shade(1.0),
+            Effect::Wave => {
+                let crest = (column as f64 + row) / WAVE_LENGTH - frame.index as f64 / WAVE_FRAMES;
+                let dip = 0.5 + 0.5 * (TAU * crest).sin();
+                Look::Shade(1.0 - WAVE_DEPTH * (1.0 - progress) * dip)
+            }
+            Effect::Pulse => {
+                let dip = 0.5 - 0.5 * (TAU * PULSES * progress).cos();
+                Look::Shade(1.0 - PULSE_DEPTH * dip)
+            }
+            Effect::Typewriter => {
+                let shown = (progress * frame.total_cells as f64).ceil() as usize;
+                if frame.cells_before + column < shown {
+                    Look::Shade(1.0)
+                } else {
+                    Look::Hidden
+                }
+            }
+            Effect::Sparkle => {
+                let roll =
+                    mix(seed ^ ((frame.index as u64) << 48) ^ ((row as u64) << 24) ^ column as u64);
+                let chance = (roll >> 11) as f64 / (1u64 << 53) as f64;
+                if chance < SPARKLE_RATE * (1.0 - progress) {
+                    Look::Shade(SPARKLE_SHADE)
+                } else {
+                    Look::Shade(1.0)
+                }
+            }
+            Effect::FadeIn => Look::Shade(progress),
+        }
+    }
+
+    /// The text to draw in `frame`: for the marquee, `text` rotated left by
+    /// its share of grapheme clusters. Lines with controls or escape
+    /// sequences are never rotated, since that could split a sequence.
+    pub(crate) fn frame_text<'a>(self, text: &'a str, frame: &Frame) -> Cow<'a, str> {
+        if self != Effect::Marquee || text.chars().any(char::is_control) {
+            return Cow::Borrowed(text);
+        }
+        let mut segmenter = Segmenter::new();
+        let starts: Vec<usize> = text
+            .char_indices()
+            .filter(|&(_, ch)| segmenter.starts_cluster(ch))
+            .map(|(idx, _)| idx)
+            .collect();
+        let shift = (starts.len() as f64 * frame.progress()) as usize;
+        match starts.get(shift) {
+            Some(&split) if shift > 0 => {
+                Cow::Owned(format!("{}{}", &text[split..], &text[..split]))
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Scales `(r, g, b)` toward black or white by a [`Look::Shade`] amount.
+pub(crate) fn shade((r, g, b): (u8, u8, u8), shade: f64) -> (u8, u8, u8) {
+    if shade == 1.0 {
+        return (r, g, b);
+    }
+    let scale = |c: u8| {
+        let c = c as f64;
+        let value = if shade <= 1.0 {
+            c * shade
+        } else {
+            c + (255.0 - c) * (shade - 1.0)
+        };
+        value.round().clamp(0.0, 255.0) as u8
+    };
+    (scale(r), scale(g), scale(b))
+}
+
+/// SplitMix64 finalizer: a well-spread hash of `value`.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(index: u32) -> Frame {
+        Frame {
+            index,
+            count: 4,
+            cells_before: 0,
+            total_cells: 8,
+        }
+    }
+
+    #[test]
+    fn last_frame_is_plain() {
+        let effects = [
+            Effect::Shift,
+            Effect::Wave,
+            Effect::Pulse,
+            Effect::Typewriter,
+            Effect::Marquee,
+            Effect::Sparkle,
+            Effect::FadeIn,
+        ];
+        for effect in effects {
+            for column in 0..8 {
+                assert_eq!(effect.look(&frame(3), column, 2.0, 7), Look::Shade(1.0));
+            }
+            assert_eq!(effect.frame_text("abcd", &frame(3)), "abcd");
+        }
+    }
+
+    #[test]
+    fn effects_change_earlier_frames() {
+        assert_eq!(Effect::FadeIn.look(&frame(0), 0, 0.0, 0), Look::Shade(0.25));
+        assert_eq!(
+            Effect::Typewriter.look(&frame(0), 1, 0.0, 0),
+            Look::Shade(1.0)
+        );
+        assert_eq!(Effect::Typewriter.look(&frame(0), 2, 0.0, 0), Look::Hidden);
+        assert_eq!(Effect::Marquee.frame_text("abcd", &frame(0)), "bcda");
+        assert_eq!(
+            Effect::Marquee.frame_text("e\u{301}fgh", &frame(1)),
+            "ghe\u{301}f"
+        );
+        assert_eq!(Effect::Marquee.frame_text("a\tb", &frame(0)), "a\tb");
+        let sparkles = |seed| {
+            (0..200)
+                .filter(|&column| {
+                    Effect::Sparkle.look(&frame(0), column, 0.0, seed) != Look::Shade(1.0)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert!(!sparkles(1).is_empty());
+        assert_eq!(sparkles(1), sparkles(1));
+        assert_ne!(sparkles(1), sparkles(2));
+    }
+
+    #[test]
+    fn shade_scales_toward_black_and_white() {
+        assert_eq!(shade((200, 100, 0), 0.5), (100, 50, 0));
+        assert_eq!(shade((200, 100, 0), 2.0), (255, 255, 255));
+        assert_eq!(shade((200, 100, 0), 1.0), (200, 100, 0));
+    }
+}
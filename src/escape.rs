@@ -0,0 +1,245 @@
+//! A VT500-style parser (after Paul Williams' DEC ANSI state machine),
+//! reduced to what a colorizer needs: where each escape sequence or control
+//! string starts and ends, so its bytes pass through untouched.
+
+const CAN: char = '\u{18}';
+const SUB: char = '\u{1a}';
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{07}';
+const C1_DCS: char = '\u{90}';
+const C1_SOS: char = '\u{98}';
+const C1_CSI: char = '\u{9b}';
+const C1_ST: char = '\u{9c}';
+const C1_OSC: char = '\u{9d}';
+const C1_PM: char = '\u{9e}';
+const C1_APC: char = '\u{9f}';
+
+/// Longest escape or CSI sequence, in characters, before it is abandoned.
+const MAX_SEQUENCE: usize = 256;
+/// Longest OSC, DCS, SOS, PM or APC string, in characters.
+const MAX_STRING: usize = 64 * 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    Csi,
+    /// OSC, terminated by ST or BEL.
+    Osc,
+    /// DCS, SOS, PM and APC, terminated by ST.
+    String,
+}
+
+/// What a character means to the sequence being parsed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// Ordinary text or a control outside any sequence.
+    Text,
+    /// Starts a sequence, ending any unfinished one.
+    Begin,
+    /// Inside a sequence.
+    Continue,
+    /// Completes the sequence, or aborts it (CAN, SUB).
+    End,
+    /// The sequence was malformed or too long and has been dropped; the
+    /// character is text again. The terminal needs a CAN to agree.
+    Abandon,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct EscapeParser {
+    state: State,
+    len: usize,
+}
+
+impl EscapeParser {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: State::Ground,
+            len: 0,
+        }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.state != State::Ground
+    }
+
+    /// Whether the sequence in progress is a CSI sequence.
+    pub(crate) fn in_csi(&self) -> bool {
+        self.state == State::Csi
+    }
+
+    /// Whether the sequence in progress is an OSC string.
+    pub(crate) fn in_osc(&self) -> bool {
+        self.state == State::Osc
+    }
+
+    pub(crate) fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub(crate) fn advance(&mut self, ch: char) -> Step {
+        // Transitions from anywhere.
+        let entered = match ch {
+            ESC => Some(State::Escape),
+            C1_CSI => Some(State::Csi),
+            C1_OSC => Some(State::Osc),
+            C1_DCS | C1_SOS | C1_PM | C1_APC => Some(State::String),
+            _ => None,
+        };
+        if let Some(state) = entered {
+            self.state = state;
+            self.len = 1;
+            return Step::Begin;
+        }
+        if self.state == State::Ground {
+            return Step::Text;
+        }
+        if matches!(ch, CAN | SUB | C1_ST) {
+            self.reset();
+            return Step::End;
+        }
+
+        self.len += 1;
+        let limit = match self.state {
+            State::Osc | State::String => MAX_STRING,
+            _ => MAX_SEQUENCE,
+        };
+        if self.len > limit {
+            self.reset();
+            return Step::Abandon;
+        }
+
+        let next = match (self.state, ch) {
+            // C0 controls inside a sequence are executed by the terminal
+            // without ending it; ignored in strings.
+            (State::Osc, BEL) => State::Ground,
+            (_, '\0'..='\u{1f}' | '\u{7f}') => return Step::Continue,
+            (State::Escape, '[') => State::Csi,
+            (State::Escape, ']') => State::Osc,
+            (State::Escape, 'P' | 'X' | '^' | '_') => State::String,
+            (State::Escape | State::EscapeIntermediate, ' '..='/') => State::EscapeIntermediate,
+            (State::Escape | State::EscapeIntermediate, '0'..='~') => State::Ground,
+            (State::Csi, ' '..='?') => State::Csi,
+            (State::Csi, '@'..='~') => State::Ground,
+            (State::Osc | State::String, _) => return Step::Continue,
+            // Anything else, non-ASCII included, cannot be part of an escape
+            // or CSI sequence.
+            _ => {
+                self.reset();
+                return Step::Abandon;
+            }
+        };
+        self.state = next;
+        if next == State::Ground {
+            self.len = 0;
+            Step::End
+        } else {
+            Step::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(input: &str) -> Vec<Step> {
+        let mut parser = EscapeParser::new();
+        input.chars().map(|ch| parser.advance(ch)).collect()
+    }
+
+    use Step::*;
+
+    #[test]
+    fn seven_and_eight_bit_sequences() {
+        assert_eq!(
+            steps("a\x1b[1mb"),
+            [Text, Begin, Continue, Continue, End, Text]
+        );
+        assert_eq!(steps("\u{9b}31mx"), [Begin, Continue, Continue, End, Text]);
+        assert_eq!(
+            steps("\u{9d}0;t\x07x"),
+            [Begin, Continue, Continue, Continue, End, Text]
+        );
+        assert_eq!(
+            steps("\x1b]0;\x1b\\x"),
+            [Begin, Continue, Continue, Continue, Begin, End, Text]
+        );
+        assert_eq!(
+            steps("\x1bPq#\u{9c}x"),
+            [Begin, Continue, Continue, Continue, End, Text]
+        );
+        assert_eq!(steps("\x1b(Bx"), [Begin, Continue, End, Text]);
+        assert_eq!(steps("\x1b7x"), [Begin, End, Text]);
+    }
+
+    #[test]
+    fn cancel_and_malformed_sequences_recover() {
+        assert_eq!(
+            steps("\x1b[12\x18x"),
+            [Begin, Continue, Continue, Continue, End, Text]
+        );
+        assert_eq!(
+            steps("\x1b]8;;\x1ax"),
+            [Begin, Continue, Continue, Continue, Continue, End, Text]
+        );
+        assert_eq!(
+            steps("\x1b[1\u{e9}x"),
+            [Begin, Continue, Continue, Abandon, Text]
+        );
+        // ESC restarts, and C0 controls inside CSI do not end it.
+        assert_eq!(
+            steps("\x1b[1\x1b[2\rm"),
+            [
+                Begin, Continue, Continue, Begin, Continue, Continue, Continue, End
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_strings_are_bounded() {
+        let mut parser = EscapeParser::new();
+        assert_eq!(parser.advance(ESC), Begin);
+        assert_eq!(parser.advance(']'), Continue);
+        let mut steps = (0..MAX_STRING).map(|_| parser.advance('a'));
+        assert!(
+            steps
+                .by_ref()
+                .take(MAX_STRING - 2)
+                .all(|step| step == Continue)
+        );
+        assert_eq!(steps.next(), Some(Abandon));
+        assert_eq!(parser.advance('a'), Text);
+
+        let mut parser = EscapeParser::new();
+        parser.advance(C1_CSI);
+        assert!((1..MAX_SEQUENCE).all(|_| parser.advance('1') == Continue));
+        assert_eq!(parser.advance('1'), Abandon);
+    }
+
+    #[test]
+    fn garbage_never_sticks() {
+        // After any input, CAN followed by text always lands in the ground state.
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut parser = EscapeParser::new();
+        for _ in 0..20_000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let pick = (state >> 33) as u32;
+            let ch = match pick % 4 {
+                0 => char::from_u32(pick >> 8 & 0x7f).unwrap(),
+                1 => char::from_u32(0x80 + (pick >> 8 & 0x1f)).unwrap(),
+                2 => ['\x1b', '[', ']', 'P', ';', '\x07', '\\'][(pick >> 8) as usize % 7],
+                _ => char::from_u32(pick >> 8 & 0xffff).unwrap_or('\u{fffd}'),
+            };
+            parser.advance(ch);
+            if pick.is_multiple_of(97) {
+                parser.advance(CAN);
+                assert_eq!(parser.advance('x'), Text);
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::str::FromStr;
+
+/// How a [`Gradient`] continues once the phase runs past its last stop.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GradientMode {
+    /// Wrap from the last stop back to the first, seamlessly.
+    #[default]
+    Cycle,
+    /// Sweep first to last, then back again.
+    Mirror,
+    /// Sweep once from where the printer started, then hold the last stop.
+    Clamp,
+}
+
+impl FromStr for GradientMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "cycle" => Ok(GradientMode::Cycle),
+            "mirror" => Ok(GradientMode::Mirror),
+            "clamp" => Ok(GradientMode::Clamp),
+            _ => Err(format!(
+                "invalid value for --gradient-mode: '{value}' (expected cycle, mirror or clamp)"
+            )),
+        }
+    }
+}
+
+/// Evenly spaced color stops, sampled along the rainbow phase.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(u8, u8, u8)>,
+    ansi256: Vec<u8>,
+}
+
+impl Gradient {
+    pub fn new(stops: Vec<(u8, u8, u8)>) -> Result<Self, String> {
+        if stops.is_empty() {
+            return Err("a gradient needs at least one color stop".to_string());
+        }
+        Ok(Self {
+            stops,
+            ansi256: Vec::new(),
+        })
+    }
+
+    /// Hand-picked 256-color palette indices walking from the first stop to
+    /// the last, used instead of quantizing the interpolated RGB.
+    pub fn with_ansi256(mut self, ramp: Vec<u8>) -> Self {
+        self.ansi256 = ramp;
+        self
+    }
+
+    /// Parses a comma separated list of `#rrggbb`, `#rgb`, `rgb(r, g, b)` or
+    /// named colors.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let stops = split_stops(spec)
+            .into_iter()
+            .map(parse_color)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(stops)
+    }
+
+    pub fn stops(&self) -> &[(u8, u8, u8)] {
+        &self.stops
+    }
+
+    /// Color at `turns` rainbow periods; one full period covers every stop.
+    pub fn sample(&self, turns: f64, mode: GradientMode) -> (u8, u8, u8) {
+        let count = self.stops.len();
+        if count == 1 {
+            return self.stops[0];
+        }
+        let (idx, frac) = self.locate(turns, mode);
+        let from = self.stops[idx];
+        let to = self.stops[(idx + 1) % count];
+        (
+            lerp(from.0, to.0, frac),
+            lerp(from.1, to.1, frac),
+            lerp(from.2, to.2, frac),
+        )
+    }
+
+    /// Palette index at `turns`, if the gradient carries a 256-color ramp.
+    pub fn sample_ansi256(&self, turns: f64, mode: GradientMode) -> Option<u8> {
+        self.sample_ansi256_biased(turns, mode, 0.0)
+    }
+
+    /// Like [`sample_ansi256`](Self::sample_ansi256), with `bias` in
+    /// `-0.5..0.5` ramp steps added before rounding, for ordered dithering.
+    pub(crate) fn sample_ansi256_biased(
+        &self,
+        turns: f64,
+        mode: GradientMode,
+        bias: f64,
+    ) -> Option<u8> {
+        let last = self.ansi256.len().checked_sub(1)?;
+        let count = self.stops.len();
+        if count == 1 {
+            return Some(self.ansi256[0]);
+        }
+        let (idx, frac) = self.locate(turns, mode);
+        if idx == count - 1 {
+            // Cycle mode's wrap from the last stop back to the first; walking
+            // the ramp backwards would flash every color in between.
+            return Some(if frac < 0.5 {
+                self.ansi256[last]
+            } else {
+                self.ansi256[0]
+            });
+        }
+        let pos = (idx as f64 + frac) / (count - 1) as f64;
+        let step = (pos * last as f64 + bias).round().clamp(0.0, last as f64);
+        Some(self.ansi256[step as usize])
+    }
+
+    /// Segment index (`stops[idx]` towards the next stop) and fraction within it.
+    fn locate(&self, turns: f64, mode: GradientMode) -> (usize, f64) {
+        let count = self.stops.len();
+        let (pos, segments) = match mode {
+            GradientMode::Cycle => (turns.rem_euclid(1.0), count),
+            GradientMode::Mirror => {
+                let bounce = turns.rem_euclid(2.0);
+                (1.0 - (bounce - 1.0).abs(), count - 1)
+            }
+            GradientMode::Clamp => (turns.clamp(0.0, 1.0), count - 1),
+        };
+        let scaled = pos * segments as f64;
+        let idx = (scaled.floor() as usize).min(segments - 1);
+        (idx, scaled - idx as f64)
+    }
+}
+
+fn lerp(from: u8, to: u8, frac: f64) -> u8 {
+    (from as f64 + (to as f64 - from as f64) * frac)
+        .round()
+        .clamp(0.0, 255.0) as u8
+}
+
+fn split_stops(spec: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (idx, ch) in spec.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(spec[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(spec[start..].trim());
+    parts
+}
+
+/// Parses `#rrggbb`, `#rgb`, `rgb(r, g, b)` or a CSS color name.
+pub fn parse_color(value: &str) -> Result<(u8, u8, u8), String> {
+    let value = value.trim();
+    let invalid = || format!("invalid color '{value}'");
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        return match digits.as_slice() {
+            [r, g, b] => Ok((r * 17, g * 17, b * 17)),
+            [r1, r2, g1, g2, b1, b2] => Ok((r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)),
+            _ => Err(invalid()),
+        };
+    }
+    let lower = value.to_ascii_lowercase();
+    if let Some(args) = lower
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels = args
+            .split(',')
+            .map(|part| part.trim().parse::<u8>().ok())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        return match channels.as_slice() {
+            [r, g, b] => Ok((*r, *g, *b)),
+            _ => Err(invalid()),
+        };
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == lower)
+        .map(|(_, rgb)| *rgb)
+        .ok_or_else(invalid)
+}
+
+const NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("black", (0, 0, 0)),
+    ("silver", (192, 192, 192)),
+    ("gray", (128, 128, 128)),
+    ("grey", (128, 128, 128)),
+    ("white", (255, 255, 255)),
+    ("maroon", (128, 0, 0)),
+    ("red", (255, 0, 0)),
+    ("purple", (128, 0, 128)),
+    ("fuchsia", (255, 0, 255)),
+    ("magenta", (255, 0, 255)),
+    ("green", (0, 128, 0)),
+    ("lime", (0, 255, 0)),
+    ("olive", (128, 128, 0)),
+    ("yellow", (255, 255, 0)),
+    ("navy", (0, 0, 128)),
+    ("blue", (0, 0, 255)),
+    ("teal", (0, 128, 128)),
+    ("aqua", (0, 255, 255)),
+    ("cyan", (0, 255, 255)),
+    ("orange", (255, 165, 0)),
+    ("gold", (255, 215, 0)),
+    ("pink", (255, 192, 203)),
+    ("hotpink", (255, 105, 180)),
+    ("deeppink", (255, 20, 147)),
+    ("crimson", (220, 20, 60)),
+    ("coral", (255, 127, 80)),
+    ("tomato", (255, 99, 71)),
+    ("salmon", (250, 128, 114)),
+    ("chocolate", (210, 105, 30)),
+    ("brown", (165, 42, 42)),
+    ("indigo", (75, 0, 130)),
+    ("violet", (238, 130, 238)),
+    ("orchid", (218, 112, 214)),
+    ("plum", (221, 160, 221)),
+    ("lavender", (230, 230, 250)),
+    ("turquoise", (64, 224, 208)),
+    ("skyblue", (135, 206, 235)),
+    ("royalblue", (65, 105, 225)),
+    ("steelblue", (70, 130, 180)),
+    ("dodgerblue", (30, 144, 255)),
+    ("seagreen", (46, 139, 87)),
+    ("springgreen", (0, 255, 127)),
+    ("chartreuse", (127, 255, 0)),
+    ("khaki", (240, 230, 140)),
+    ("beige", (245, 245, 220)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_rgb_and_named_stops() {
+        let gradient = Gradient::parse("#ff0080, rgb(121, 40, 202),#2AFADF,#fff,navy").unwrap();
+        assert_eq!(
+            gradient.stops(),
+            &[
+                (255, 0, 128),
+                (121, 40, 202),
+                (42, 250, 223),
+                (255, 255, 255),
+                (0, 0, 128)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_colors() {
+        assert!(Gradient::parse("#12345").is_err());
+        assert!(Gradient::parse("rgb(1,2)").is_err());
+        assert!(Gradient::parse("red,notacolor").is_err());
+        assert!(Gradient::parse("").is_err());
+    }
+
+    #[test]
+    fn sample_modes_cover_the_stops() {
+        let gradient = Gradient::parse("#000000,#ffffff").unwrap();
+        assert_eq!(gradient.sample(0.0, GradientMode::Cycle), (0, 0, 0));
+        assert_eq!(gradient.sample(0.5, GradientMode::Cycle), (255, 255, 255));
+        assert_eq!(gradient.sample(1.0, GradientMode::Cycle), (0, 0, 0));
+        assert_eq!(gradient.sample(1.0, GradientMode::Mirror), (255, 255, 255));
+        assert_eq!(gradient.sample(1.5, GradientMode::Mirror), (128, 128, 128));
+        assert_eq!(gradient.sample(-3.0, GradientMode::Clamp), (0, 0, 0));
+        assert_eq!(gradient.sample(7.0, GradientMode::Clamp), (255, 255, 255));
+    }
+
+    #[test]
+    fn ansi256_ramp_follows_the_stops() {
+        let plain = Gradient::parse("red,blue").unwrap();
+        assert_eq!(plain.sample_ansi256(0.0, GradientMode::Cycle), None);
+
+        let gradient = plain.with_ansi256(vec![196, 128, 21]);
+        assert_eq!(gradient.sample_ansi256(0.0, GradientMode::Clamp), Some(196));
+        assert_eq!(gradient.sample_ansi256(0.5, GradientMode::Clamp), Some(128));
+        assert_eq!(gradient.sample_ansi256(1.0, GradientMode::Clamp), Some(21));
+        assert_eq!(gradient.sample_ansi256(0.45, GradientMode::Cycle), Some(21));
+        assert_eq!(gradient.sample_ansi256(0.6, GradientMode::Cycle), Some(21));
+        assert_eq!(gradient.sample_ansi256(0.9, GradientMode::Cycle), Some(196));
+    }
+}
@@ -0,0 +1,219 @@
+//! Extended grapheme cluster boundaries (UAX #29), fed one character at a
+//! time so clusters may span reads.
+
+mod table;
+
+const HANGUL_FIRST: u32 = 0xAC00;
+const HANGUL_LAST: u32 = 0xD7A3;
+const HANGUL_T_COUNT: u32 = 28;
+
+/// Grapheme_Cluster_Break values, with the Extended_Pictographic and
+/// Indic_Conjunct_Break properties folded in as extra variants.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Category {
+    Other,
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    Pictographic,
+    /// An Indic_Conjunct_Break consonant; otherwise `Other`.
+    Consonant,
+    /// An Indic_Conjunct_Break linker (virama); otherwise `Extend`.
+    Linker,
+}
+
+impl Category {
+    pub(crate) fn of(ch: char) -> Self {
+        let cp = ch as u32;
+        if cp < 0x80 {
+            return match ch {
+                '\r' => Category::Cr,
+                '\n' => Category::Lf,
+                c if c.is_ascii_control() => Category::Control,
+                _ => Category::Other,
+            };
+        }
+        if (HANGUL_FIRST..=HANGUL_LAST).contains(&cp) {
+            return if (cp - HANGUL_FIRST).is_multiple_of(HANGUL_T_COUNT) {
+                Category::Lv
+            } else {
+                Category::Lvt
+            };
+        }
+        table::CATEGORIES
+            .binary_search_by(|&(first, last, _)| {
+                if last < cp {
+                    std::cmp::Ordering::Less
+                } else if first > cp {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .map_or(Category::Other, |idx| table::CATEGORIES[idx].2)
+    }
+
+    fn is_control(self) -> bool {
+        matches!(self, Category::Cr | Category::Lf | Category::Control)
+    }
+
+    fn is_extend(self) -> bool {
+        matches!(self, Category::Extend | Category::Linker)
+    }
+}
+
+/// How much of an emoji ZWJ sequence (GB11) has been seen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Emoji {
+    None,
+    /// A pictograph followed by any extenders.
+    Pictograph,
+    /// ... and then a ZWJ.
+    Joined,
+}
+
+/// How much of an Indic conjunct (GB9c) has been seen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Conjunct {
+    None,
+    Consonant,
+    /// A consonant followed by at least one linker.
+    Linked,
+}
+
+/// Streaming boundary detector: tells, for each character in turn, whether
+/// it starts a new cluster.
+#[derive(Clone, Debug)]
+pub(crate) struct Segmenter {
+    prev: Option<Category>,
+    /// An odd number of regional indicators precedes (GB12, GB13).
+    odd_regional: bool,
+    emoji: Emoji,
+    conjunct: Conjunct,
+}
+
+impl Segmenter {
+    pub(crate) fn new() -> Self {
+        Self {
+            prev: None,
+            odd_regional: false,
+            emoji: Emoji::None,
+            conjunct: Conjunct::None,
+        }
+    }
+
+    /// Forgets the current cluster, so the next character starts one.
+    pub(crate) fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Whether `ch` begins a new cluster rather than extending the last one.
+    pub(crate) fn starts_cluster(&mut self, ch: char) -> bool {
+        let cat = Category::of(ch);
+        let boundary = match self.prev {
+            None => true,
+            Some(prev) => self.is_boundary(prev, cat),
+        };
+        self.odd_regional = cat == Category::RegionalIndicator
+            && !(self.prev == Some(Category::RegionalIndicator) && self.odd_regional);
+        self.emoji = match cat {
+            Category::Pictographic => Emoji::Pictograph,
+            _ if cat.is_extend() && self.emoji == Emoji::Pictograph => Emoji::Pictograph,
+            Category::Zwj if self.emoji == Emoji::Pictograph => Emoji::Joined,
+            _ => Emoji::None,
+        };
+        self.conjunct = match (cat, self.conjunct) {
+            (Category::Consonant, _) => Conjunct::Consonant,
+            (Category::Linker, Conjunct::Consonant | Conjunct::Linked) => Conjunct::Linked,
+            (Category::Extend | Category::Zwj, state) => state,
+            _ => Conjunct::None,
+        };
+        self.prev = Some(cat);
+        boundary
+    }
+
+    fn is_boundary(&self, prev: Category, cat: Category) -> bool {
+        use Category::*;
+        match (prev, cat) {
+            (Cr, Lf) => false,
+            (prev, cat) if prev.is_control() || cat.is_control() => true,
+            (L, L | V | Lv | Lvt) | (Lv | V, V | T) | (Lvt | T, T) => false,
+            (_, Extend | Zwj | Linker | SpacingMark) | (Prepend, _) => false,
+            (_, Consonant) if self.conjunct == Conjunct::Linked => false,
+            (Zwj, Pictographic) if self.emoji == Emoji::Joined => false,
+            (RegionalIndicator, RegionalIndicator) => !self.odd_regional,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clusters(text: &str) -> Vec<String> {
+        let mut segmenter = Segmenter::new();
+        let mut out: Vec<String> = Vec::new();
+        for ch in text.chars() {
+            if segmenter.starts_cluster(ch) {
+                out.push(String::new());
+            }
+            out.last_mut().unwrap().push(ch);
+        }
+        out
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_base() {
+        assert_eq!(clusters("e\u{301}a"), ["e\u{301}", "a"]);
+        assert_eq!(clusters("a\u{308}\u{330}!"), ["a\u{308}\u{330}", "!"]);
+        assert_eq!(clusters("\r\n\u{301}"), ["\r\n", "\u{301}"]);
+    }
+
+    #[test]
+    fn emoji_sequences() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(clusters(&format!("{family}x")), [family, "x"]);
+        let thumbs = "\u{1F44D}\u{1F3FD}";
+        assert_eq!(clusters(thumbs), [thumbs]);
+        // A ZWJ after a letter does not glue the next pictograph on.
+        assert_eq!(clusters("a\u{200D}\u{1F600}"), ["a\u{200D}", "\u{1F600}"]);
+    }
+
+    #[test]
+    fn flags_pair_regional_indicators() {
+        let flags = "\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}\u{1F1EC}";
+        assert_eq!(
+            clusters(flags),
+            ["\u{1F1E9}\u{1F1EA}", "\u{1F1EB}\u{1F1F7}", "\u{1F1EC}"]
+        );
+    }
+
+    #[test]
+    fn hangul_jamo_form_syllables() {
+        // L V T, then a precomposed LV syllable extended by T.
+        assert_eq!(
+            clusters("\u{1100}\u{1161}\u{11A8}\u{AC00}\u{11A8}\u{AC01}"),
+            ["\u{1100}\u{1161}\u{11A8}", "\u{AC00}\u{11A8}", "\u{AC01}"]
+        );
+        assert_eq!(clusters("\u{11A8}\u{1100}"), ["\u{11A8}", "\u{1100}"]);
+    }
+
+    #[test]
+    fn indic_conjuncts_and_prepend() {
+        // Devanagari "ksa": KA, VIRAMA, SSA.
+        assert_eq!(clusters("\u{915}\u{94D}\u{937}"), ["\u{915}\u{94D}\u{937}"]);
+        assert_eq!(clusters("\u{915}\u{93F}"), ["\u{915}\u{93F}"]);
+        assert_eq!(clusters("\u{600}1"), ["\u{600}1"]);
+    }
+}
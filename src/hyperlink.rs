@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+/// How the text of OSC 8 hyperlinks is colored.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LinkStyle {
+    /// Like any other text.
+    #[default]
+    Rainbow,
+    /// One color for the whole link, taken from where it starts.
+    Solid,
+    /// The rainbow, underlined.
+    Underline,
+    /// The rainbow shifted half a turn, so links stand out from their line.
+    Complement,
+}
+
+impl FromStr for LinkStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "rainbow" => Ok(LinkStyle::Rainbow),
+            "solid" => Ok(LinkStyle::Solid),
+            "underline" => Ok(LinkStyle::Underline),
+            "complement" => Ok(LinkStyle::Complement),
+            _ => Err(format!(
+                "invalid value for --link-style: '{value}' \
+                 (expected rainbow, solid, underline or complement)"
+            )),
+        }
+    }
+}
+
+/// Reads an OSC body as it streams past, far enough to tell an OSC 8
+/// hyperlink (`8;params;uri`) from other OSC strings.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum LinkScan {
+    /// Nothing read yet.
+    #[default]
+    Start,
+    /// Read `8`.
+    Eight,
+    /// Inside the parameters.
+    Params,
+    /// Past the parameters; whether the URI has any characters.
+    Uri { empty: bool },
+    /// Some other OSC.
+    Other,
+}
+
+impl LinkScan {
+    pub(crate) fn feed(&mut self, ch: char) {
+        *self = match (*self, ch) {
+            (LinkScan::Start, '8') => LinkScan::Eight,
+            (LinkScan::Eight, ';') => LinkScan::Params,
+            (LinkScan::Params, ';') => LinkScan::Uri { empty: true },
+            (LinkScan::Params, _) => LinkScan::Params,
+            (LinkScan::Uri { .. }, _) => LinkScan::Uri { empty: false },
+            _ => LinkScan::Other,
+        };
+    }
+
+    /// For a finished OSC 8: `Some(true)` when it opens a link, `Some(false)`
+    /// when it closes one.
+    pub(crate) fn opens_link(self) -> Option<bool> {
+        match self {
+            LinkScan::Uri { empty } => Some(!empty),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(body: &str) -> Option<bool> {
+        let mut scan = LinkScan::default();
+        body.chars().for_each(|ch| scan.feed(ch));
+        scan.opens_link()
+    }
+
+    #[test]
+    fn recognises_opening_and_closing_links() {
+        assert_eq!(scan("8;;https://example.com"), Some(true));
+        assert_eq!(scan("8;id=a:b;file:///tmp"), Some(true));
+        assert_eq!(scan("8;;"), Some(false));
+        assert_eq!(scan("8;id=a;"), Some(false));
+        assert_eq!(scan("0;title"), None);
+        assert_eq!(scan("88;;x"), None);
+        assert_eq!(scan("8"), None);
+    }
+}
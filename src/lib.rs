@@ -0,0 +1,55 @@
+//! The rainbow engine behind the `lolcat` binary.
+//!
+//! ```
+//! use neo_lolcat::{ColorMode, Config, Printer};
+//!
+//! let config = Config::builder().spread(8.0).freq(0.3).seed(42).build().unwrap();
+//! let mut printer = Printer::new(config, ColorMode::TrueColor);
+//! let mut out = Vec::new();
+//! printer.print_text("hello, world\n", &mut out).unwrap();
+//! printer.finalize(&mut out).unwrap();
+//! assert!(out.starts_with(b"\x1b[38;2;"));
+//! ```
+
+mod cast;
+mod color;
+mod colorspace;
+mod config;
+mod detect;
+mod direction;
+mod effect;
+mod escape;
+mod gradient;
+mod grapheme;
+mod hyperlink;
+mod json;
+mod printer;
+mod query;
+mod rainbow;
+mod sgr;
+mod stream;
+mod tabs;
+mod terminfo;
+mod theme;
+mod width;
+mod writer;
+
+pub use cast::{CastWriter, Clock, play_cast};
+pub use color::{
+    ColorMode, Quantize, rgb_to_ansi8, rgb_to_ansi16, rgb_to_ansi256, rgb_to_ansi256_fast,
+};
+pub use colorspace::ColorSpace;
+pub use config::{Config, ConfigBuilder};
+pub use detect::{ColorSupport, choose_color_mode, detect_color_support};
+pub use direction::{Direction, Granularity, block_center};
+pub use effect::Effect;
+pub use gradient::{Gradient, GradientMode, parse_color};
+pub use hyperlink::LinkStyle;
+pub use printer::Printer;
+pub use query::{TerminalReport, query_terminal, terminal_height, terminal_width};
+pub use rainbow::{Background, random_seed_offset};
+pub use sgr::ExistingColor;
+pub use stream::{StreamError, process_stream};
+pub use tabs::TabStops;
+pub use theme::{THEMES, Theme, find_theme};
+pub use writer::RainbowWriter;
@@ -1,41 +1,65 @@
 use std::{
-    borrow::Cow,
     env,
+    f64::consts::TAU,
     fs::File,
-    io::{self, BufReader, IsTerminal, Read, Write},
-    process, thread,
-    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    io::{self, BufReader, BufWriter, IsTerminal, Read, Write},
+    process::{self, Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
 };
 
-const RESET: &str = "\x1b[0m";
-const RESET_FG: &str = "\x1b[39m";
-const RESET_BG: &str = "\x1b[49m";
-const SAVE_CURSOR: &str = "\x1b7";
-const RESTORE_CURSOR: &str = "\x1b8";
-const HIDE_CURSOR: &str = "\x1b[?25l";
-const SHOW_CURSOR: &str = "\x1b[?25h";
-const READ_CHUNK: usize = 64 * 1024;
-const PENDING_CAP: usize = 4096;
-const SHIFT_COS: f64 = -0.5;
-const SHIFT_SIN: f64 = 0.866_025_403_784_438_6;
+use neo_lolcat::{
+    CastWriter, Clock, Config, ConfigBuilder, Direction, Gradient, GradientMode, Printer,
+    StreamError, THEMES, block_center, choose_color_mode, detect_color_support, find_theme,
+    play_cast, process_stream, query_terminal, random_seed_offset, terminal_height, terminal_width,
+};
 
 const HELP_TEXT: &str = r#"Usage: lolcat [OPTION]... [FILE]...
 
 Concatenate FILE(s), or standard input, to standard output.
 With no FILE, or when FILE is -, read standard input.
 
-  -p, --spread=<f>      Rainbow spread (default: 3.0)
-  -F, --freq=<f>        Rainbow frequency (default: 0.1)
-  -S, --seed=<i>        Rainbow seed, 0 = random (default: 0)
-  -a, --animate         Enable psychedelics
-  -d, --duration=<i>    Animation duration (default: 12)
-  -s, --speed=<f>       Animation speed (default: 20.0)
-  -i, --invert          Invert fg and bg
-  -t, --truecolor       24-bit (truecolor)
-  -f, --force           Force color even when stdout is not a tty
-  -D, --debug           Print internal diagnostics
-  -v, --version         Print version and exit
-  -h, --help            Show this message
+  -p, --spread=<f>            Rainbow spread (default: 3.0)
+  -F, --freq=<f>              Rainbow frequency (default: 0.1)
+  -S, --seed=<i>              Rainbow seed, 0 = random (default: 0)
+  -a, --animate[=forever]     Enable psychedelics; forever runs until a key
+      --animate-block[=<n>]   Animate the whole input, or n lines, at once
+      --effect=<e>            wave, pulse, typewriter, marquee, sparkle or fade-in
+  -d, --duration=<i>          Animation duration (default: 12)
+  -s, --speed=<f>             Animation speed (default: 20.0)
+  -i, --invert                Invert fg and bg
+  -t, --truecolor             24-bit (truecolor)
+      --colors=<n>            Color mode: 8, 16, 256 or 24bit (default: auto)
+      --quantize=<q>          256-color fitting: fast or accurate (default: accurate)
+      --granularity=<g>       Color per char, word, line or paragraph
+      --dither                Ordered dithering for 256/16/8-color output
+      --tabs=<n|list>         Tab width, or comma-separated stops (default: 8)
+      --keep-tabs             Pass tabs through uncolored
+      --existing-color=<m>    Input colors: override, preserve or strip
+      --link-style=<s>        Link text: rainbow, solid, underline or complement
+      --reset-between-files   Drop unfinished escapes and reset attributes per file
+      --direction=<d>         horizontal, vertical, diagonal, angle=DEG or radial
+      --center=<col,row>      Radial center (default: middle of the input)
+      --gradient=<list>       Comma-separated stops (#hex, rgb(), names)
+      --gradient-mode=<m>     cycle, mirror or clamp (default: cycle)
+      --colorspace=<s>        Hue sweep: sine, hsl or oklch (default: sine)
+      --lightness=<f>         hsl/oklch lightness, 0-1 (default: 0.5 / 0.75)
+      --chroma=<f>            oklch chroma 0-0.4, hsl saturation 0-1
+      --theme=<name>          Use a built-in gradient (pride, fire, ocean, ...)
+      --list-themes           Show every built-in theme
+      --background=<b>        Tune colors for a dark or light background
+      --query-terminal        Ask the terminal for truecolor and its background
+      --record=<file>         Also write the output as an asciicast v2 recording
+      --record-only           Only record, with frame times simulated, not waited
+      --play=<file>           Re-color an asciicast recording, --speed times as fast
+  -f, --force                 Force color, ignoring tty checks and NO_COLOR
+  -D, --debug                 Print internal diagnostics
+  -v, --version               Print version and exit
+  -h, --help                  Show this message
 
 Examples:
   lolcat f - g      Output f's contents, then stdin, then g's contents.
@@ -47,34 +71,49 @@ neo-lolcat home page: <https://github.com/skyline69/neo-lolcat/>
 Report lolcat translation bugs to <http://speaklolcat.com/>
 "#;
 
+const SWATCH_WIDTH: usize = 32;
+const QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+/// Columns and rows a recording claims when the terminal size is unknown.
+const CAST_SIZE: (usize, usize) = (80, 24);
+
 fn main() {
     process::exit(run());
 }
 
 fn run() -> i32 {
     let args: Vec<String> = env::args().skip(1).collect();
-    let config = match Config::parse(&args) {
-        Ok(cfg) => cfg,
+    let cli = match Cli::parse(&args) {
+        Ok(cli) => cli,
         Err(err) => {
             eprintln!("lolcat: {err}");
             return 1;
         }
     };
 
-    if config.version {
+    if cli.version {
         println!("neo-lolcat {} (c)2025 Ö. Efe D.", env!("CARGO_PKG_VERSION"));
         return 0;
     }
 
-    if config.help {
-        if let Err(err) = print_help(&config) {
+    if cli.help {
+        if let Err(err) = print_help(&cli) {
             eprintln!("lolcat: failed to render help: {err}");
             return 1;
         }
         return 0;
     }
 
-    match execute(&config) {
+    if cli.list_themes {
+        if let Err(err) = print_themes(&cli)
+            && err.kind() != io::ErrorKind::BrokenPipe
+        {
+            eprintln!("lolcat: {err}");
+            return 1;
+        }
+        return 0;
+    }
+
+    match execute(&cli) {
         RunStatus::Success => 0,
         RunStatus::Reported => 1,
         RunStatus::BrokenPipe => 0,
@@ -85,79 +124,260 @@ fn run() -> i32 {
     }
 }
 
-fn debug_log(cfg: &Config, msg: &str) {
-    if cfg.debug {
+fn debug_log(cli: &Cli, msg: &str) {
+    if cli.debug {
         eprintln!("[lolcat] {msg}");
     }
 }
 
-fn print_help(config: &Config) -> io::Result<()> {
+fn print_help(cli: &Cli) -> io::Result<()> {
     let stdout = io::stdout();
     let mut handle = stdout.lock();
-    let mut help_cfg = config.clone();
-    help_cfg.force = true;
-    help_cfg.animate = false;
-    help_cfg.spread = 8.0;
-    help_cfg.freq = 0.3;
+    let help_cfg = Config::builder()
+        .spread(8.0)
+        .freq(0.3)
+        .invert(cli.config.invert())
+        .colors(cli.config.colors())
+        .build()
+        .map_err(io::Error::other)?;
     let color_mode = choose_color_mode(&help_cfg);
-    let mut printer = Printer::new(&help_cfg, true, color_mode, random_seed_offset(8192.0));
-    printer.print_text(HELP_TEXT, &mut handle)?;
-    match printer.finalize(&mut handle) {
+    let mut printer = Printer::new(help_cfg, color_mode).with_offset(random_seed_offset(8192.0));
+    match printer
+        .print_text(HELP_TEXT, &mut handle)
+        .and_then(|()| printer.finalize(&mut handle))
+    {
         Ok(()) => Ok(()),
         Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
         Err(err) => Err(err),
     }
 }
 
-fn execute(config: &Config) -> RunStatus {
+fn print_themes(cli: &Cli) -> io::Result<()> {
     let stdout = io::stdout();
-    let stdout_is_tty = stdout.is_terminal();
+    let use_color = detect_color_support(&cli.config, cli.force, stdout.is_terminal()).enabled;
     let mut handle = stdout.lock();
-    let use_color = stdout_is_tty || config.force;
-    let color_mode = if use_color {
-        choose_color_mode(config)
+    let swatch = "█".repeat(SWATCH_WIDTH);
+    for theme in THEMES {
+        write!(handle, "{:<10} ", theme.name)?;
+        if use_color {
+            let swatch_cfg = Config::builder()
+                .gradient(theme.gradient())
+                .gradient_mode(GradientMode::Clamp)
+                .freq(1.0)
+                .spread((SWATCH_WIDTH - 1) as f64 / TAU)
+                .colors(cli.config.colors())
+                .build()
+                .map_err(io::Error::other)?;
+            let color_mode = choose_color_mode(&swatch_cfg);
+            let mut printer = Printer::new(swatch_cfg, color_mode).with_offset(0.0);
+            printer.print_text(&swatch, &mut handle)?;
+            printer.finalize(&mut handle)?;
+            write!(handle, " ")?;
+        }
+        writeln!(handle, "{}", theme.description)?;
+    }
+    handle.flush()
+}
+
+fn execute(cli: &Cli) -> RunStatus {
+    let stdout = io::stdout();
+    let stdout_is_tty = stdout.is_terminal();
+    let mut config = cli.config.clone();
+    // A recording is played back on a terminal, wherever stdout goes.
+    let mut support = detect_color_support(&config, cli.force, stdout_is_tty);
+    if cli.query_terminal && support.enabled && stdout_is_tty && io::stdin().is_terminal() {
+        match query_terminal(QUERY_TIMEOUT) {
+            Some(report) => {
+                debug_log(cli, &format!("terminal report: {report:?}"));
+                support.apply_report(&config, &report);
+                if let (None, Some(background)) = (config.background(), report.background_kind()) {
+                    match config.to_builder().background(background).build() {
+                        Ok(tuned) => config = tuned,
+                        Err(err) => debug_log(cli, &err),
+                    }
+                }
+            }
+            None => debug_log(cli, "terminal query unavailable"),
+        }
+    }
+    // A recording is always colored. Stdout keeps to its own rules and gets
+    // the input as is when only the recording is colored.
+    let use_color = support.enabled || cli.record.is_some();
+    let color_mode = if support.enabled {
+        support.mode
     } else {
-        ColorMode::Ansi256
+        choose_color_mode(&config)
     };
+    let echo = !support.enabled && cli.record.is_some() && !cli.record_only;
     debug_log(
-        config,
+        cli,
         &format!(
-            "use_color={}, mode={:?}, animate={}, spread={}, freq={}",
-            use_color, color_mode, config.animate, config.spread, config.freq
+            "use_color={} ({}), mode={:?} ({}), echo={echo}",
+            support.enabled, support.enabled_reason, color_mode, support.mode_reason
         ),
     );
-    let mut printer = Printer::new(config, use_color, color_mode, initial_offset(config.seed));
+    debug_log(
+        cli,
+        &format!(
+            "animate={}, spread={}, freq={}, background={:?}",
+            config.animate(),
+            config.spread(),
+            config.freq(),
+            config.background().unwrap_or_default()
+        ),
+    );
+
+    // Block animation moves the cursor up over lines that may have wrapped.
+    let mut columns = if use_color && config.animate_block() {
+        terminal_width()
+    } else {
+        None
+    };
+    debug_log(cli, &format!("terminal width {columns:?}"));
 
     let stdin = io::stdin();
     let mut stdin_lock = stdin.lock();
-    let files: Vec<String> = if config.files.is_empty() {
+    let files: Vec<String> = if cli.files.is_empty() {
         vec!["-".to_string()]
     } else {
-        config.files.clone()
+        cli.files.clone()
+    };
+    // Keys typed as input are left alone. Dropped last, after the printer
+    // has restored what it changed.
+    let types_input =
+        cli.play.is_none() && files.iter().any(|path| path == "-") && stdin.is_terminal();
+    let guard = (use_color && config.animate() && config.forever() && !types_input)
+        .then(TerminalGuard::install);
+
+    let clock = if cli.record_only {
+        Clock::simulated()
+    } else {
+        Clock::real()
+    };
+    let cast = match &cli.record {
+        Some(path) => {
+            let width = columns.or_else(terminal_width).unwrap_or(CAST_SIZE.0);
+            let height = terminal_height().unwrap_or(CAST_SIZE.1);
+            if config.animate_block() {
+                columns = Some(width);
+            }
+            let cast = File::create(path).and_then(|file| {
+                CastWriter::new(BufWriter::new(file), width, height, clock.clone())
+            });
+            match cast {
+                Ok(cast) => Some(cast),
+                Err(err) => {
+                    eprintln!("{}", describe_error(path, &err));
+                    return RunStatus::Reported;
+                }
+            }
+        }
+        None => None,
+    };
+    let mut handle = Output {
+        stdout: (!cli.record_only && !echo).then(|| stdout.lock()),
+        cast,
+    };
+    let prepare = |printer: Printer| {
+        let printer = printer
+            .with_terminal_width(columns)
+            .with_clock(clock.clone());
+        match &guard {
+            Some(guard) => printer.with_stop(guard.stop_flag()),
+            None => printer,
+        }
     };
 
-    for path in files {
-        debug_log(config, &format!("processing source '{path}'"));
+    if let Some(path) = &cli.play {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("{}", describe_error(path, &err));
+                return RunStatus::Reported;
+            }
+        };
+        let mut printer = prepare(Printer::new(config.clone(), color_mode).with_color(use_color));
+        let mut plain = Printer::new(config, color_mode).with_color(false);
+        let mut stdout = io::stdout();
+        let mut outputs: Vec<(&mut Printer, &mut dyn Write)> = vec![(&mut printer, &mut handle)];
+        if echo {
+            outputs.push((&mut plain, &mut stdout));
+        }
+        let speed = cli.speed.unwrap_or(1.0);
+        let played = play_cast(BufReader::new(file), &mut outputs, speed);
+        drop(outputs);
+        return match played {
+            Err(StreamError::BrokenPipe) => {
+                stop(&mut printer, &mut handle, StreamError::BrokenPipe)
+            }
+            Err(StreamError::Io(err)) => {
+                let _ = printer.finalize(&mut handle);
+                eprintln!("lolcat: {path}: {err}");
+                RunStatus::Reported
+            }
+            Ok(()) => match printer.finalize(&mut handle) {
+                Ok(()) => RunStatus::Success,
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => RunStatus::BrokenPipe,
+                Err(err) => RunStatus::Io(err),
+            },
+        };
+    }
+
+    // A radial gradient without --center is centered on the whole input, so
+    // it has to be read before anything is printed.
+    if use_color && config.direction() == Direction::Radial && config.center().is_none() {
+        let (blocks, failure) = read_sources(cli, &files, &mut stdin_lock);
+        let center = block_center(&String::from_utf8_lossy(&blocks.concat()), config.tabs());
+        debug_log(cli, &format!("radial center {center:?}"));
+        config = match config.to_builder().center(center).build() {
+            Ok(config) => config,
+            Err(err) => return RunStatus::Io(io::Error::other(err)),
+        };
+        let mut printer = prepare(Printer::new(config, color_mode));
+        for (idx, block) in blocks.iter().enumerate() {
+            if idx > 0
+                && cli.reset_between_files
+                && let Err(err) = printer.end_source(&mut handle)
+            {
+                return stop(&mut printer, &mut handle, err.into());
+            }
+            if let Err(err) = process_stream(Echo::new(&block[..], echo), &mut handle, &mut printer)
+            {
+                return stop(&mut printer, &mut handle, err);
+            }
+        }
+        return match (printer.finalize(&mut handle), failure) {
+            (Err(err), _) if err.kind() == io::ErrorKind::BrokenPipe => RunStatus::BrokenPipe,
+            (Err(err), _) => RunStatus::Io(err),
+            (Ok(()), Some(status)) => status,
+            (Ok(()), None) => RunStatus::Success,
+        };
+    }
+
+    let mut printer = prepare(Printer::new(config, color_mode).with_color(use_color));
+    for (idx, path) in files.iter().enumerate() {
+        debug_log(cli, &format!("processing source '{path}'"));
+        if idx > 0
+            && cli.reset_between_files
+            && let Err(err) = printer.end_source(&mut handle)
+        {
+            return stop(&mut printer, &mut handle, err.into());
+        }
         let result = if path == "-" {
-            process_stream(&mut stdin_lock, &mut handle, &mut printer)
+            process_stream(Echo::new(&mut stdin_lock, echo), &mut handle, &mut printer)
         } else {
-            match File::open(&path) {
-                Ok(file) => process_stream(file, &mut handle, &mut printer),
+            match File::open(path) {
+                Ok(file) => process_stream(Echo::new(file, echo), &mut handle, &mut printer),
                 Err(err) => {
-                    eprintln!("{}", describe_error(&path, &err));
+                    eprintln!("{}", describe_error(path, &err));
                     let _ = printer.finalize(&mut handle);
                     return RunStatus::Reported;
                 }
             }
         };
 
-        match result {
-            Ok(()) => {}
-            Err(StreamError::BrokenPipe) => return RunStatus::BrokenPipe,
-            Err(StreamError::Io(err)) => {
-                let _ = printer.finalize(&mut handle);
-                return RunStatus::Io(err);
-            }
+        if let Err(err) = result {
+            return stop(&mut printer, &mut handle, err);
         }
     }
 
@@ -168,157 +388,187 @@ fn execute(config: &Config) -> RunStatus {
     }
 }
 
-fn process_stream<R: Read>(
-    reader: R,
-    writer: &mut dyn Write,
-    printer: &mut Printer,
-) -> Result<(), StreamError> {
-    if !printer.use_color {
-        let mut reader = BufReader::new(reader);
-        io::copy(&mut reader, writer).map_err(StreamError::from)?;
-        return Ok(());
-    }
-
-    if printer.cfg.animate {
-        process_stream_buffered(reader, writer, printer)
-    } else {
-        process_stream_streaming(reader, writer, printer)
+/// Ends the run on a failed write or read. The printer is still finalized,
+/// so whatever output is left, a recording when stdout went away, gets its
+/// hyperlink and attributes closed.
+fn stop(printer: &mut Printer, writer: &mut dyn Write, err: StreamError) -> RunStatus {
+    let _ = printer.finalize(writer);
+    match err {
+        StreamError::BrokenPipe => RunStatus::BrokenPipe,
+        StreamError::Io(err) => RunStatus::Io(err),
     }
 }
 
-fn process_stream_buffered<R: Read>(
-    reader: R,
-    writer: &mut dyn Write,
-    printer: &mut Printer,
-) -> Result<(), StreamError> {
-    let mut reader = BufReader::new(reader);
-    let mut chunk = [0u8; READ_CHUNK];
-    let mut line_buf = Vec::new();
-    loop {
-        let read = reader.read(&mut chunk).map_err(StreamError::from)?;
-        if read == 0 {
-            if !line_buf.is_empty() {
-                flush_line(&mut line_buf, false, printer, writer)?;
+/// Reads every source into memory, one block per source, stopping at the
+/// first failure; open errors are reported on the spot like the streaming
+/// path does.
+fn read_sources(
+    cli: &Cli,
+    files: &[String],
+    stdin: &mut dyn Read,
+) -> (Vec<Vec<u8>>, Option<RunStatus>) {
+    let mut blocks = Vec::new();
+    for path in files {
+        debug_log(cli, &format!("buffering source '{path}'"));
+        let mut block = Vec::new();
+        let result = if path == "-" {
+            stdin.read_to_end(&mut block)
+        } else {
+            match File::open(path) {
+                Ok(mut file) => file.read_to_end(&mut block),
+                Err(err) => {
+                    eprintln!("{}", describe_error(path, &err));
+                    return (blocks, Some(RunStatus::Reported));
+                }
             }
-            break;
+        };
+        blocks.push(block);
+        if let Err(err) = result {
+            return (blocks, Some(RunStatus::Io(err)));
         }
+    }
+    (blocks, None)
+}
 
-        let mut start = 0;
-        for (idx, &byte) in chunk[..read].iter().enumerate() {
-            if byte == b'\n' {
-                line_buf.extend_from_slice(&chunk[start..idx]);
-                flush_line(&mut line_buf, true, printer, writer)?;
-                start = idx + 1;
-            }
-        }
+/// Lets a key end `--animate=forever`.
+///
+/// While it lives the controlling terminal hands over keys unbuffered and
+/// unechoed, and a background thread polling it sets the stop flag on the
+/// first one. Dropping the guard stops the thread and restores the
+/// terminal settings. Ctrl-C and Ctrl-Z keep their usual meaning.
+struct TerminalGuard {
+    stop: Arc<AtomicBool>,
+    /// Tells the poller to finish.
+    done: Arc<AtomicBool>,
+    poller: Option<JoinHandle<()>>,
+    saved: Option<String>,
+}
 
-        if start < read {
-            line_buf.extend_from_slice(&chunk[start..read]);
+impl TerminalGuard {
+    /// Takes over the controlling terminal; without one, or without
+    /// `stty`, nothing but a signal ends the animation.
+    fn install() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let done = Arc::new(AtomicBool::new(false));
+        let tty = File::open("/dev/tty").ok();
+        let saved = tty.as_ref().and_then(|tty| {
+            let saved = stty(tty, &["-g"])?;
+            // `min 0 time 1` turns every read into a 100ms poll.
+            stty(tty, &["-icanon", "-echo", "min", "0", "time", "1"])?;
+            Some(saved.trim().to_string())
+        });
+        let poller = tty.filter(|_| saved.is_some()).map(|mut tty| {
+            let (stop, done) = (Arc::clone(&stop), Arc::clone(&done));
+            thread::spawn(move || {
+                let mut key = [0u8; 1];
+                while !done.load(Ordering::Relaxed) {
+                    if matches!(tty.read(&mut key), Ok(1)) {
+                        stop.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                }
+            })
+        });
+        Self {
+            stop,
+            done,
+            poller,
+            saved,
         }
     }
-    printer.flush_pending(writer).map_err(StreamError::from)
+
+    fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
 }
 
-fn process_stream_streaming<R: Read>(
-    mut reader: R,
-    writer: &mut dyn Write,
-    printer: &mut Printer,
-) -> Result<(), StreamError> {
-    let mut buffer = [0u8; READ_CHUNK + 4];
-    let mut carry = 0usize;
-
-    'outer: loop {
-        let read = reader
-            .read(&mut buffer[carry..])
-            .map_err(StreamError::from)?;
-        if read == 0 {
-            break;
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+        if let Some(poller) = self.poller.take() {
+            let _ = poller.join();
         }
-        let total = carry + read;
-        let mut offset = 0usize;
-
-        while offset < total {
-            match std::str::from_utf8(&buffer[offset..total]) {
-                Ok(valid) => {
-                    consume_segment(valid, printer, writer).map_err(StreamError::from)?;
-                    offset = total;
-                }
-                Err(err) => {
-                    let valid_up_to = err.valid_up_to();
-                    if valid_up_to > 0 {
-                        let slice = std::str::from_utf8(&buffer[offset..offset + valid_up_to])
-                            .expect("validator provided a valid prefix");
-                        consume_segment(slice, printer, writer).map_err(StreamError::from)?;
-                        offset += valid_up_to;
-                        continue;
-                    }
-                    if let Some(error_len) = err.error_len() {
-                        printer
-                            .write_replacement(writer)
-                            .map_err(StreamError::from)?;
-                        offset += error_len;
-                        continue;
-                    }
-                    carry = total - offset;
-                    buffer.copy_within(offset..total, 0);
-                    continue 'outer;
-                }
-            }
+        if let Some(saved) = &self.saved
+            && let Ok(tty) = File::open("/dev/tty")
+        {
+            let _ = stty(&tty, &[saved]);
         }
-        carry = 0;
     }
+}
 
-    if carry > 0 {
-        printer
-            .write_replacement(writer)
-            .map_err(StreamError::from)?;
-    }
+/// Runs `stty` on `tty`, returning what it printed.
+fn stty(tty: &File, args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone().ok()?)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
 
-    printer.flush_pending(writer).map_err(StreamError::from)
+/// Copies everything read through it to stdout, for when only the
+/// recording is colored.
+struct Echo<R> {
+    inner: R,
+    stdout: Option<io::Stdout>,
 }
 
-fn consume_segment(
-    segment: &str,
-    printer: &mut Printer<'_>,
-    writer: &mut dyn Write,
-) -> io::Result<()> {
-    if segment.is_empty() {
-        return Ok(());
-    }
-    let mut start = 0;
-    for (idx, ch) in segment.char_indices() {
-        if ch == '\n' {
-            if idx > start {
-                printer.write_plain_segment(&segment[start..idx], writer)?;
-            }
-            printer.finish_line(writer)?;
-            start = idx + ch.len_utf8();
+impl<R: Read> Echo<R> {
+    fn new(inner: R, echo: bool) -> Self {
+        Self {
+            inner,
+            stdout: echo.then(io::stdout),
         }
     }
-    if start < segment.len() {
-        printer.write_plain_segment(&segment[start..], writer)?;
+}
+
+impl<R: Read> Read for Echo<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(stdout) = &mut self.stdout {
+            stdout.write_all(&buf[..read])?;
+            stdout.flush()?;
+        }
+        Ok(read)
     }
-    Ok(())
 }
 
-fn flush_line(
-    line_buf: &mut Vec<u8>,
-    had_newline: bool,
-    printer: &mut Printer,
-    writer: &mut dyn Write,
-) -> Result<(), StreamError> {
-    if line_buf.is_empty() && !had_newline {
-        return Ok(());
+/// Standard output, the `--record` recording, or both.
+struct Output<'a> {
+    stdout: Option<io::StdoutLock<'a>>,
+    cast: Option<CastWriter<BufWriter<File>>>,
+}
+
+impl Write for Output<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A failed stdout is dropped, so the recording keeps everything up
+        // to the end of the run.
+        let stdout = match &mut self.stdout {
+            Some(stdout) => stdout.write_all(buf),
+            None => Ok(()),
+        };
+        if stdout.is_err() {
+            self.stdout = None;
+        }
+        if let Some(cast) = &mut self.cast {
+            cast.write_all(buf)?;
+        }
+        stdout.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(stdout) = &mut self.stdout {
+            stdout.flush()?;
+        }
+        if let Some(cast) = &mut self.cast {
+            cast.flush()?;
+        }
+        Ok(())
     }
-    let text = match String::from_utf8_lossy(line_buf) {
-        Cow::Owned(s) => s,
-        Cow::Borrowed(s) => s.to_string(),
-    };
-    printer
-        .print_line(&text, had_newline, writer)
-        .map_err(StreamError::from)?;
-    line_buf.clear();
-    Ok(())
 }
 
 fn describe_error(path: &str, err: &io::Error) -> String {
@@ -334,46 +584,28 @@ fn describe_error(path: &str, err: &io::Error) -> String {
     }
 }
 
-#[derive(Clone, Debug)]
-struct Config {
-    spread: f64,
-    freq: f64,
-    seed: u64,
-    animate: bool,
-    duration: u32,
-    speed: f64,
-    invert: bool,
-    truecolor: bool,
+#[derive(Clone, Debug, Default)]
+struct Cli {
+    config: Config,
     force: bool,
     debug: bool,
     version: bool,
     help: bool,
+    list_themes: bool,
+    query_terminal: bool,
+    reset_between_files: bool,
+    record: Option<String>,
+    record_only: bool,
+    play: Option<String>,
+    /// `--speed` as given, which scales `--play`.
+    speed: Option<f64>,
     files: Vec<String>,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            spread: 3.0,
-            freq: 0.1,
-            seed: 0,
-            animate: false,
-            duration: 12,
-            speed: 20.0,
-            invert: false,
-            truecolor: false,
-            force: false,
-            debug: false,
-            version: false,
-            help: false,
-            files: Vec::new(),
-        }
-    }
-}
-
-impl Config {
+impl Cli {
     fn parse(args: &[String]) -> Result<Self, String> {
-        let mut cfg = Config::default();
+        let mut cli = Cli::default();
+        let mut opts = Config::builder();
         let mut iter = args.iter().peekable();
         let mut files = Vec::new();
         while let Some(arg) = iter.next() {
@@ -381,24 +613,34 @@ impl Config {
                 files.extend(iter.map(|s| s.to_string()));
                 break;
             } else if arg.starts_with("--") {
-                Self::parse_long(arg, &mut cfg, &mut iter)?;
+                Self::parse_long(arg, &mut cli, &mut opts, &mut iter)?;
             } else if arg.starts_with('-') && arg.len() > 1 {
-                Self::parse_short(arg, &mut cfg, &mut iter)?;
+                Self::parse_short(arg, &mut cli, &mut opts, &mut iter)?;
             } else {
                 files.push(arg.to_string());
             }
         }
-        cfg.files = files;
-        if !cfg.debug && env::var("LOLCAT_DEBUG").is_ok() {
-            cfg.debug = true;
+        cli.files = files;
+        if !cli.debug && env::var("LOLCAT_DEBUG").is_ok() {
+            cli.debug = true;
+        }
+        cli.config = opts.build()?;
+        if cli.record_only && cli.record.is_none() {
+            return Err("--record-only needs --record=<file>".to_string());
         }
-        cfg.validate()?;
-        Ok(cfg)
+        if cli.play.is_some() && !cli.files.is_empty() {
+            return Err("--play cannot be combined with input files".to_string());
+        }
+        if cli.record_only && cli.config.forever() {
+            return Err("--record-only cannot record --animate=forever".to_string());
+        }
+        Ok(cli)
     }
 
     fn parse_long<'a, I>(
         arg: &str,
-        cfg: &mut Config,
+        cli: &mut Cli,
+        opts: &mut ConfigBuilder,
         iter: &mut std::iter::Peekable<I>,
     ) -> Result<(), String>
     where
@@ -409,35 +651,117 @@ impl Config {
         let value = parts.next();
         match name {
             "spread" => {
-                cfg.spread = Self::parse_f64("spread", value, iter)?;
+                opts.spread(Self::parse_f64("spread", value, iter)?);
             }
             "freq" => {
-                cfg.freq = Self::parse_f64("freq", value, iter)?;
+                opts.freq(Self::parse_f64("freq", value, iter)?);
             }
             "seed" => {
-                cfg.seed = Self::parse_u64("seed", value, iter)?;
+                opts.seed(Self::parse_u64("seed", value, iter)?);
             }
             "animate" => {
-                cfg.animate = true;
-                if let Some(val) = value {
-                    Self::override_duration(cfg, "animate", val.to_string())?;
+                opts.animate(true);
+                if value == Some("forever") {
+                    opts.forever(true);
+                } else if let Some(val) = value {
+                    Self::override_duration(opts, "animate", val.to_string())?;
                 } else if let Some(raw) = Self::consume_numeric_arg(iter) {
-                    Self::override_duration(cfg, "animate", raw)?;
+                    Self::override_duration(opts, "animate", raw)?;
                 }
             }
+            "animate-block" => {
+                opts.animate(true).animate_block(true);
+                if let Some(val) = value {
+                    let lines = parse_u64_value("animate-block", val.to_string())?;
+                    opts.block_lines(Some(lines as usize));
+                }
+            }
+            "effect" => {
+                opts.animate(true)
+                    .effect(Self::parse_str("effect", value, iter)?.parse()?);
+            }
             "duration" => {
                 let val = Self::parse_f64("duration", value, iter)?;
-                cfg.duration = float_duration_to_frames(val)?;
+                opts.duration(float_duration_to_frames(val)?);
             }
             "speed" => {
-                cfg.speed = Self::parse_f64("speed", value, iter)?;
+                let speed = Self::parse_f64("speed", value, iter)?;
+                opts.speed(speed);
+                cli.speed = Some(speed);
+            }
+            "invert" => {
+                opts.invert(true);
+            }
+            "truecolor" => {
+                opts.truecolor(true);
+            }
+            "colors" => {
+                opts.colors(Some(Self::parse_str("colors", value, iter)?.parse()?));
+            }
+            "direction" => {
+                opts.direction(Self::parse_str("direction", value, iter)?.parse()?);
+            }
+            "center" => {
+                opts.center(parse_center(&Self::parse_str("center", value, iter)?)?);
+            }
+            "granularity" => {
+                opts.granularity(Self::parse_str("granularity", value, iter)?.parse()?);
+            }
+            "dither" => {
+                opts.dither(true);
+            }
+            "tabs" => {
+                opts.tabs(Self::parse_str("tabs", value, iter)?.parse()?);
+            }
+            "keep-tabs" => {
+                opts.keep_tabs(true);
+            }
+            "existing-color" => {
+                opts.existing_color(Self::parse_str("existing-color", value, iter)?.parse()?);
+            }
+            "link-style" => {
+                opts.link_style(Self::parse_str("link-style", value, iter)?.parse()?);
+            }
+            "quantize" => {
+                opts.quantize(Self::parse_str("quantize", value, iter)?.parse()?);
+            }
+            "gradient" => {
+                let spec = Self::parse_str("gradient", value, iter)?;
+                let gradient = Gradient::parse(&spec)
+                    .map_err(|err| format!("invalid value for --gradient: {err}"))?;
+                opts.gradient(gradient);
             }
-            "invert" => cfg.invert = true,
-            "truecolor" => cfg.truecolor = true,
-            "force" => cfg.force = true,
-            "debug" => cfg.debug = true,
-            "version" => cfg.version = true,
-            "help" => cfg.help = true,
+            "colorspace" => {
+                opts.colorspace(Self::parse_str("colorspace", value, iter)?.parse()?);
+            }
+            "lightness" => {
+                opts.lightness(Self::parse_f64("lightness", value, iter)?);
+            }
+            "chroma" => {
+                opts.chroma(Self::parse_f64("chroma", value, iter)?);
+            }
+            "theme" => {
+                let name = Self::parse_str("theme", value, iter)?;
+                let theme = find_theme(&name)
+                    .ok_or_else(|| format!("unknown theme '{name}' (see --list-themes)"))?;
+                opts.gradient(theme.gradient());
+            }
+            "list-themes" => cli.list_themes = true,
+            "background" => {
+                opts.background(Self::parse_str("background", value, iter)?.parse()?);
+            }
+            "query-terminal" => cli.query_terminal = true,
+            "reset-between-files" => cli.reset_between_files = true,
+            "record" => cli.record = Some(Self::parse_str("record", value, iter)?),
+            "record-only" => cli.record_only = true,
+            "play" => cli.play = Some(Self::parse_str("play", value, iter)?),
+            "gradient-mode" => {
+                opts.gradient_mode(Self::parse_str("gradient-mode", value, iter)?.parse()?);
+            }
+            "force" => cli.force = true,
+            "debug" => cli.debug = true,
+            "version" => cli.version = true,
+            "help" => cli.help = true,
             _ => {
                 if !name.is_empty() {
                     return Err(format!("unknown option '--{name}'"));
@@ -449,7 +773,8 @@ impl Config {
 
     fn parse_short<'a, I>(
         arg: &str,
-        cfg: &mut Config,
+        cli: &mut Cli,
+        opts: &mut ConfigBuilder,
         iter: &mut std::iter::Peekable<I>,
     ) -> Result<(), String>
     where
@@ -460,41 +785,49 @@ impl Config {
             match ch {
                 'p' => {
                     let value = Self::attached_value(&mut chars, iter, "-p")?;
-                    cfg.spread = parse_f64_value("spread", value)?;
+                    opts.spread(parse_f64_value("spread", value)?);
                     break;
                 }
                 'F' => {
                     let value = Self::attached_value(&mut chars, iter, "-F")?;
-                    cfg.freq = parse_f64_value("freq", value)?;
+                    opts.freq(parse_f64_value("freq", value)?);
                     break;
                 }
                 'S' => {
                     let value = Self::attached_value(&mut chars, iter, "-S")?;
-                    cfg.seed = parse_u64_value("seed", value)?;
+                    opts.seed(parse_u64_value("seed", value)?);
                     break;
                 }
                 'd' => {
                     let value = Self::attached_value(&mut chars, iter, "-d")?;
-                    cfg.duration = float_duration_to_frames(parse_f64_value("duration", value)?)?;
+                    opts.duration(float_duration_to_frames(parse_f64_value(
+                        "duration", value,
+                    )?)?);
                     break;
                 }
                 's' => {
                     let value = Self::attached_value(&mut chars, iter, "-s")?;
-                    cfg.speed = parse_f64_value("speed", value)?;
+                    let speed = parse_f64_value("speed", value)?;
+                    opts.speed(speed);
+                    cli.speed = Some(speed);
                     break;
                 }
                 'a' => {
-                    cfg.animate = true;
+                    opts.animate(true);
                     if let Some(raw) = Self::consume_numeric_arg(iter) {
-                        Self::override_duration(cfg, "animate", raw)?;
+                        Self::override_duration(opts, "animate", raw)?;
                     }
                 }
-                'i' => cfg.invert = true,
-                't' => cfg.truecolor = true,
-                'f' => cfg.force = true,
-                'D' => cfg.debug = true,
-                'v' => cfg.version = true,
-                'h' => cfg.help = true,
+                'i' => {
+                    opts.invert(true);
+                }
+                't' => {
+                    opts.truecolor(true);
+                }
+                'f' => cli.force = true,
+                'D' => cli.debug = true,
+                'v' => cli.version = true,
+                'h' => cli.help = true,
                 other => {
                     return Err(format!("unknown option '-{other}'"));
                 }
@@ -503,17 +836,20 @@ impl Config {
         Ok(())
     }
 
-    fn validate(&self) -> Result<(), String> {
-        if self.spread < 0.1 {
-            return Err("--spread must be >= 0.1".to_string());
-        }
-        if self.speed < 0.1 {
-            return Err("--speed must be >= 0.1".to_string());
-        }
-        if self.duration == 0 {
-            return Err("--duration must be >= 1".to_string());
+    fn parse_str<'a, I>(
+        name: &str,
+        value: Option<&str>,
+        iter: &mut std::iter::Peekable<I>,
+    ) -> Result<String, String>
+    where
+        I: Iterator<Item = &'a String>,
+    {
+        if let Some(val) = value {
+            return Ok(val.to_string());
         }
-        Ok(())
+        iter.next()
+            .cloned()
+            .ok_or_else(|| format!("--{name} requires a value"))
     }
 
     fn parse_f64<'a, I>(
@@ -568,9 +904,9 @@ impl Config {
         }
     }
 
-    fn override_duration(cfg: &mut Config, flag: &str, raw: String) -> Result<(), String> {
+    fn override_duration(opts: &mut ConfigBuilder, flag: &str, raw: String) -> Result<(), String> {
         let val = parse_f64_value(flag, raw)?;
-        cfg.duration = float_duration_to_frames(val)?;
+        opts.duration(float_duration_to_frames(val)?);
         Ok(())
     }
 
@@ -601,6 +937,19 @@ fn parse_f64_value(name: &str, value: String) -> Result<f64, String> {
         .map_err(|_| format!("invalid value for --{name}: '{value}'"))
 }
 
+fn parse_center(value: &str) -> Result<(f64, f64), String> {
+    let invalid = || format!("invalid value for --center: '{value}' (expected COL,ROW)");
+    let (col, row) = value.split_once(',').ok_or_else(invalid)?;
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .ok_or_else(invalid)
+    };
+    Ok((parse(col)?, parse(row)?))
+}
+
 fn parse_u64_value(name: &str, value: String) -> Result<u64, String> {
     value
         .parse::<u64>()
@@ -614,518 +963,13 @@ enum RunStatus {
     Io(io::Error),
 }
 
-#[derive(Debug)]
-enum StreamError {
-    BrokenPipe,
-    Io(io::Error),
-}
-
-impl From<io::Error> for StreamError {
-    fn from(err: io::Error) -> Self {
-        if err.kind() == io::ErrorKind::BrokenPipe {
-            StreamError::BrokenPipe
-        } else {
-            StreamError::Io(err)
-        }
-    }
-}
-
-#[derive(Copy, Clone, Debug)]
-enum ColorMode {
-    TrueColor,
-    Ansi256,
-}
-
-fn choose_color_mode(config: &Config) -> ColorMode {
-    let env_term = env::var("COLORTERM").ok();
-    choose_color_mode_from(config, env_term.as_deref())
-}
-
-fn choose_color_mode_from(config: &Config, env_term: Option<&str>) -> ColorMode {
-    if config.truecolor || detects_truecolor_from(env_term) {
-        ColorMode::TrueColor
-    } else {
-        ColorMode::Ansi256
-    }
-}
-
-fn detects_truecolor_from(term: Option<&str>) -> bool {
-    term.map(|value| {
-        let lower = value.to_ascii_lowercase();
-        lower.contains("truecolor") || lower.contains("24bit")
-    })
-    .unwrap_or(false)
-}
-
-fn initial_offset(seed: u64) -> f64 {
-    if seed == 0 {
-        random_seed_offset(256.0)
-    } else {
-        (seed % 256) as f64
-    }
-}
-
-fn random_seed_offset(range: f64) -> f64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|dur| (dur.as_nanos() % (range as u128)) as f64)
-        .unwrap_or(0.0)
-}
-
-struct Printer<'a> {
-    cfg: &'a Config,
-    os: f64,
-    use_color: bool,
-    color_mode: ColorMode,
-    cursor_hidden: bool,
-    line_active: bool,
-    escape_state: EscapeState,
-    phase: RainbowState,
-    rot: RainbowRot,
-    buffer: SmallBuf,
-}
-
-impl<'a> Printer<'a> {
-    fn new(cfg: &'a Config, use_color: bool, color_mode: ColorMode, offset: f64) -> Self {
-        let angle = cfg.freq * offset;
-        Self {
-            cfg,
-            os: offset,
-            use_color,
-            color_mode,
-            cursor_hidden: false,
-            line_active: false,
-            escape_state: EscapeState::Idle,
-            phase: RainbowState::from_angle(angle),
-            rot: RainbowRot::new(cfg.freq / cfg.spread),
-            buffer: SmallBuf::new(),
-        }
-    }
-
-    fn finalize(&mut self, writer: &mut dyn Write) -> io::Result<()> {
-        if self.cursor_hidden {
-            self.buffer.push(writer, SHOW_CURSOR.as_bytes())?;
-            self.cursor_hidden = false;
-        }
-        if self.use_color {
-            self.buffer.push(writer, RESET.as_bytes())?;
-        }
-        self.buffer.flush(writer)?;
-        writer.flush()
-    }
-
-    fn print_text(&mut self, text: &str, writer: &mut dyn Write) -> io::Result<()> {
-        for line in text.split_inclusive('\n') {
-            let (body, newline) = if let Some(stripped) = line.strip_suffix('\n') {
-                (stripped, true)
-            } else {
-                (line, false)
-            };
-            self.print_line(body, newline, writer)?;
-        }
-        Ok(())
-    }
-
-    fn print_line(
-        &mut self,
-        text: &str,
-        had_newline: bool,
-        writer: &mut dyn Write,
-    ) -> io::Result<()> {
-        if self.cfg.animate && !text.is_empty() {
-            self.animate_line(text, had_newline, writer)
-        } else {
-            self.print_plain_line(text, had_newline, writer)
-        }
-    }
-
-    fn animate_line(
-        &mut self,
-        text: &str,
-        had_newline: bool,
-        writer: &mut dyn Write,
-    ) -> io::Result<()> {
-        if !self.cursor_hidden {
-            self.buffer.push(writer, HIDE_CURSOR.as_bytes())?;
-            self.cursor_hidden = true;
-        }
-        self.buffer.push(writer, SAVE_CURSOR.as_bytes())?;
-        let original = self.os;
-        let frames = self.cfg.duration;
-        let frame_time = Duration::from_secs_f64(1.0 / self.cfg.speed);
-        let mut next_frame = Instant::now();
-        for _ in 0..frames {
-            self.buffer.push(writer, RESTORE_CURSOR.as_bytes())?;
-            self.os += self.cfg.spread;
-            self.print_plain_line(text, false, writer)?;
-            self.buffer.flush(writer)?;
-            writer.flush()?;
-            next_frame += frame_time;
-            let now = Instant::now();
-            if next_frame > now {
-                thread::sleep(next_frame - now);
-            } else {
-                next_frame = now;
-            }
-        }
-        self.os = original;
-        if had_newline {
-            self.buffer.push(writer, b"\n")?;
-            self.os += 1.0;
-        }
-        self.buffer.flush(writer)?;
-        Ok(())
-    }
-
-    fn print_plain_line(
-        &mut self,
-        text: &str,
-        had_newline: bool,
-        writer: &mut dyn Write,
-    ) -> io::Result<()> {
-        if !self.use_color {
-            self.buffer.flush(writer)?;
-            writer.write_all(text.as_bytes())?;
-            if had_newline {
-                writer.write_all(b"\n")?;
-            }
-            return Ok(());
-        }
-
-        self.line_active = false;
-        self.escape_state = EscapeState::Idle;
-        self.write_plain_segment(text, writer)?;
-        if had_newline {
-            self.finish_line(writer)?;
-        } else {
-            self.line_active = false;
-        }
-        self.escape_state = EscapeState::Idle;
-        Ok(())
-    }
-
-    fn write_plain_segment(&mut self, text: &str, writer: &mut dyn Write) -> io::Result<()> {
-        debug_assert!(self.use_color);
-        for ch in text.chars() {
-            if self.escape_state.is_active() {
-                self.feed_escape(ch, writer)?;
-                continue;
-            }
-            if ch == '\x1b' {
-                self.begin_escape(writer)?;
-                continue;
-            }
-            if ch == '\t' {
-                for _ in 0..8 {
-                    self.write_visible_char(' ', writer)?;
-                }
-                continue;
-            }
-            self.write_visible_char(ch, writer)?;
-        }
-        Ok(())
-    }
-
-    fn write_visible_char(&mut self, ch: char, writer: &mut dyn Write) -> io::Result<()> {
-        self.ensure_line_active();
-        let (r, g, b) = self.phase.channels();
-        let encoded = &mut [0u8; 4];
-        let glyph = ch.encode_utf8(encoded);
-        let mut block = [0u8; 64];
-        let mut len = match (self.cfg.invert, self.color_mode) {
-            (invert, ColorMode::TrueColor) => build_truecolor_prefix(&mut block, invert, r, g, b),
-            (invert, ColorMode::Ansi256) => {
-                let idx = rgb_to_ansi256(r, g, b);
-                build_ansi_prefix(&mut block, invert, idx)
-            }
-        };
-        block[len..len + glyph.len()].copy_from_slice(glyph.as_bytes());
-        len += glyph.len();
-        let reset = if self.cfg.invert {
-            RESET_BG.as_bytes()
-        } else {
-            RESET_FG.as_bytes()
-        };
-        block[len..len + reset.len()].copy_from_slice(reset);
-        len += reset.len();
-        self.buffer.push(writer, &block[..len])?;
-        self.phase.advance(self.rot);
-        Ok(())
-    }
-
-    fn finish_line(&mut self, writer: &mut dyn Write) -> io::Result<()> {
-        self.buffer.push(writer, b"\n")?;
-        self.os += 1.0;
-        self.line_active = false;
-        Ok(())
-    }
-
-    fn ensure_line_active(&mut self) {
-        if !self.line_active {
-            self.line_active = true;
-            self.phase.reset(self.cfg.freq * self.os);
-        }
-    }
-
-    fn begin_escape(&mut self, writer: &mut dyn Write) -> io::Result<()> {
-        self.buffer.push(writer, b"\x1b")?;
-        self.escape_state = EscapeState::Start;
-        Ok(())
-    }
-
-    fn feed_escape(&mut self, ch: char, writer: &mut dyn Write) -> io::Result<()> {
-        let mut buf = [0u8; 4];
-        let encoded = ch.encode_utf8(&mut buf);
-        self.buffer.push(writer, encoded.as_bytes())?;
-        self.escape_state.advance(ch);
-        Ok(())
-    }
-
-    fn write_replacement(&mut self, writer: &mut dyn Write) -> io::Result<()> {
-        self.write_visible_char('\u{FFFD}', writer)
-    }
-
-    fn flush_pending(&mut self, writer: &mut dyn Write) -> io::Result<()> {
-        self.buffer.flush(writer)
-    }
-}
-
-fn build_truecolor_prefix(buf: &mut [u8], invert: bool, r: u8, g: u8, b: u8) -> usize {
-    let mut len = 0;
-    buf[len] = 0x1b;
-    len += 1;
-    buf[len] = b'[';
-    len += 1;
-    buf[len] = if invert { b'4' } else { b'3' };
-    len += 1;
-    buf[len] = b'8';
-    len += 1;
-    buf[len] = b';';
-    len += 1;
-    buf[len] = b'2';
-    len += 1;
-    buf[len] = b';';
-    len += 1;
-    len += append_decimal_u8(&mut buf[len..], r);
-    buf[len] = b';';
-    len += 1;
-    len += append_decimal_u8(&mut buf[len..], g);
-    buf[len] = b';';
-    len += 1;
-    len += append_decimal_u8(&mut buf[len..], b);
-    buf[len] = b'm';
-    len + 1
-}
-
-fn build_ansi_prefix(buf: &mut [u8], invert: bool, idx: u8) -> usize {
-    let mut len = 0;
-    buf[len] = 0x1b;
-    len += 1;
-    buf[len] = b'[';
-    len += 1;
-    buf[len] = if invert { b'4' } else { b'3' };
-    len += 1;
-    buf[len] = b'8';
-    len += 1;
-    buf[len] = b';';
-    len += 1;
-    buf[len] = b'5';
-    len += 1;
-    buf[len] = b';';
-    len += 1;
-    len += append_decimal_u8(&mut buf[len..], idx);
-    buf[len] = b'm';
-    len + 1
-}
-
-fn append_decimal_u8(dst: &mut [u8], value: u8) -> usize {
-    debug_assert!(dst.len() >= 3);
-    let hundreds = value / 100;
-    let tens = (value % 100) / 10;
-    let ones = value % 10;
-    let mut len = 0;
-    if hundreds != 0 {
-        dst[len] = b'0' + hundreds;
-        len += 1;
-        dst[len] = b'0' + tens;
-        len += 1;
-        dst[len] = b'0' + ones;
-        len += 1;
-    } else if tens != 0 {
-        dst[len] = b'0' + tens;
-        len += 1;
-        dst[len] = b'0' + ones;
-        len += 1;
-    } else {
-        dst[len] = b'0' + ones;
-        len += 1;
-    }
-    len
-}
-
-struct SmallBuf {
-    data: [u8; PENDING_CAP],
-    len: usize,
-}
-
-impl SmallBuf {
-    fn new() -> Self {
-        Self {
-            data: [0u8; PENDING_CAP],
-            len: 0,
-        }
-    }
-
-    fn push(&mut self, writer: &mut dyn Write, chunk: &[u8]) -> io::Result<()> {
-        if chunk.is_empty() {
-            return Ok(());
-        }
-        if chunk.len() >= self.data.len() {
-            self.flush(writer)?;
-            return writer.write_all(chunk);
-        }
-        if self.len + chunk.len() > self.data.len() {
-            self.flush(writer)?;
-        }
-        self.data[self.len..self.len + chunk.len()].copy_from_slice(chunk);
-        self.len += chunk.len();
-        Ok(())
-    }
-
-    fn flush(&mut self, writer: &mut dyn Write) -> io::Result<()> {
-        if self.len > 0 {
-            writer.write_all(&self.data[..self.len])?;
-            self.len = 0;
-        }
-        Ok(())
-    }
-}
-
-#[derive(Copy, Clone)]
-enum EscapeState {
-    Idle,
-    Start,
-    Csi,
-    Osc { saw_esc: bool },
-    StringTerm { saw_esc: bool },
-    Fe,
-}
-
-impl EscapeState {
-    fn is_active(self) -> bool {
-        !matches!(self, EscapeState::Idle)
-    }
-
-    fn advance(&mut self, ch: char) {
-        match self {
-            EscapeState::Idle => {}
-            EscapeState::Start => {
-                *self = match ch {
-                    '[' => EscapeState::Csi,
-                    ']' => EscapeState::Osc { saw_esc: false },
-                    'P' | 'X' | '^' | '_' => EscapeState::StringTerm { saw_esc: false },
-                    c if (' '..='/').contains(&c) => EscapeState::Fe,
-                    _ => EscapeState::Idle,
-                };
-            }
-            EscapeState::Csi => {
-                if ('@'..='~').contains(&ch) {
-                    *self = EscapeState::Idle;
-                }
-            }
-            EscapeState::Osc { saw_esc } => {
-                if ch == '\u{07}' || (*saw_esc && ch == '\\') {
-                    *self = EscapeState::Idle;
-                    return;
-                }
-                *saw_esc = ch == '\x1b';
-            }
-            EscapeState::StringTerm { saw_esc } => {
-                if *saw_esc && ch == '\\' {
-                    *self = EscapeState::Idle;
-                    return;
-                }
-                *saw_esc = ch == '\x1b';
-            }
-            EscapeState::Fe => {
-                *self = EscapeState::Idle;
-            }
-        }
-    }
-}
-
-#[derive(Copy, Clone)]
-struct RainbowState {
-    sin: f64,
-    cos: f64,
-}
-
-impl RainbowState {
-    fn from_angle(angle: f64) -> Self {
-        let (sin, cos) = angle.sin_cos();
-        Self { sin, cos }
-    }
-
-    fn reset(&mut self, angle: f64) {
-        let (sin, cos) = angle.sin_cos();
-        self.sin = sin;
-        self.cos = cos;
-    }
-
-    fn advance(&mut self, rot: RainbowRot) {
-        let sin = self.sin * rot.cos + self.cos * rot.sin;
-        let cos = self.cos * rot.cos - self.sin * rot.sin;
-        self.sin = sin;
-        self.cos = cos;
-    }
-
-    fn channels(&self) -> (u8, u8, u8) {
-        (
-            encode_component(self.sin),
-            encode_component(self.sin * SHIFT_COS + self.cos * SHIFT_SIN),
-            encode_component(self.sin * SHIFT_COS - self.cos * SHIFT_SIN),
-        )
-    }
-}
-
-#[derive(Copy, Clone)]
-struct RainbowRot {
-    cos: f64,
-    sin: f64,
-}
-
-impl RainbowRot {
-    fn new(delta: f64) -> Self {
-        let (sin, cos) = delta.sin_cos();
-        Self { cos, sin }
-    }
-}
-
-fn encode_component(value: f64) -> u8 {
-    value.mul_add(127.0, 128.0).round().clamp(0.0, 255.0) as u8
-}
-
-fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
-    if r == g && g == b {
-        if r < 8 {
-            16
-        } else if r > 248 {
-            231
-        } else {
-            ((r as u16 - 8) * 24 / 247) as u8 + 232
-        }
-    } else {
-        let r = (r as u16 * 5 / 255) as u8;
-        let g = (g as u16 * 5 / 255) as u8;
-        let b = (b as u16 * 5 / 255) as u8;
-        16 + 36 * r + 6 * g + b
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::{self, Read};
+    use neo_lolcat::{
+        Background, ColorMode, ColorSpace, Effect, ExistingColor, GradientMode, Granularity,
+        LinkStyle, Quantize, TabStops,
+    };
 
     fn strings(args: &[&str]) -> Vec<String> {
         args.iter().map(|s| s.to_string()).collect()
@@ -1133,17 +977,18 @@ mod tests {
 
     #[test]
     fn parse_defaults_without_args() {
-        let cfg = Config::parse(&[]).unwrap();
-        assert_eq!(cfg.spread, 3.0);
-        assert_eq!(cfg.freq, 0.1);
-        assert_eq!(cfg.seed, 0);
-        assert!(!cfg.animate);
-        assert_eq!(cfg.duration, 12);
-        assert_eq!(cfg.speed, 20.0);
-        assert!(!cfg.invert);
-        assert!(!cfg.truecolor);
-        assert!(!cfg.force);
-        assert!(cfg.files.is_empty());
+        let cli = Cli::parse(&[]).unwrap();
+        let cfg = &cli.config;
+        assert_eq!(cfg.spread(), 3.0);
+        assert_eq!(cfg.freq(), 0.1);
+        assert_eq!(cfg.seed(), 0);
+        assert!(!cfg.animate());
+        assert_eq!(cfg.duration(), 12);
+        assert_eq!(cfg.speed(), 20.0);
+        assert!(!cfg.invert());
+        assert!(!cfg.truecolor());
+        assert!(!cli.force);
+        assert!(cli.files.is_empty());
     }
 
     #[test]
@@ -1161,166 +1006,260 @@ mod tests {
             "-",
             "bar",
         ]);
-        let cfg = Config::parse(&args).unwrap();
-        assert_eq!(cfg.spread, 5.0);
-        assert!((cfg.freq - 0.2).abs() < f64::EPSILON);
-        assert_eq!(cfg.seed, 7);
-        assert!(cfg.animate);
-        assert_eq!(cfg.duration, 6);
-        assert!((cfg.speed - 15.5).abs() < f64::EPSILON);
-        assert!(cfg.invert);
-        assert!(cfg.truecolor);
-        assert!(cfg.force);
+        let cli = Cli::parse(&args).unwrap();
+        let cfg = &cli.config;
+        assert_eq!(cfg.spread(), 5.0);
+        assert!((cfg.freq() - 0.2).abs() < f64::EPSILON);
+        assert_eq!(cfg.seed(), 7);
+        assert!(cfg.animate());
+        assert_eq!(cfg.duration(), 6);
+        assert!((cfg.speed() - 15.5).abs() < f64::EPSILON);
+        assert!(cfg.invert());
+        assert!(cfg.truecolor());
+        assert!(cli.force);
         assert_eq!(
-            cfg.files,
+            cli.files,
             vec!["foo".to_string(), "-".to_string(), "bar".to_string()]
         );
     }
 
     #[test]
     fn parse_requires_values() {
-        let err = Config::parse(&strings(&["-p"])).unwrap_err();
+        let err = Cli::parse(&strings(&["-p"])).unwrap_err();
         assert!(err.contains("-p"), "unexpected error: {err}");
         assert!(err.contains("requires"));
     }
 
     #[test]
     fn animate_option_consumes_numeric_duration() {
-        let cfg = Config::parse(&strings(&["--animate", "1.4"])).unwrap();
-        assert!(cfg.animate);
-        assert_eq!(cfg.duration, 1);
-        assert!(cfg.files.is_empty());
+        let cli = Cli::parse(&strings(&["--animate", "1.4"])).unwrap();
+        assert!(cli.config.animate());
+        assert_eq!(cli.config.duration(), 1);
+        assert!(cli.files.is_empty());
     }
 
     #[test]
     fn animate_short_form_consumes_numeric_duration() {
-        let cfg = Config::parse(&strings(&["-a", "2"])).unwrap();
-        assert!(cfg.animate);
-        assert_eq!(cfg.duration, 2);
-        assert!(cfg.files.is_empty());
+        let cli = Cli::parse(&strings(&["-a", "2"])).unwrap();
+        assert!(cli.config.animate());
+        assert_eq!(cli.config.duration(), 2);
+        assert!(cli.files.is_empty());
+    }
+
+    #[test]
+    fn parse_animate_block() {
+        let cli = Cli::parse(&strings(&["--animate-block"])).unwrap();
+        assert!(cli.config.animate() && cli.config.animate_block());
+        assert_eq!(cli.config.block_lines(), None);
+        let cli = Cli::parse(&strings(&["--animate-block=20"])).unwrap();
+        assert_eq!(cli.config.block_lines(), Some(20));
+        let err = Cli::parse(&strings(&["--animate-block=0"])).unwrap_err();
+        assert!(err.contains("--animate-block"), "unexpected error: {err}");
+        assert!(Cli::parse(&strings(&["--animate-block=x"])).is_err());
+    }
+
+    #[test]
+    fn parse_effect() {
+        let cli = Cli::parse(&strings(&["--effect", "fade-in"])).unwrap();
+        assert!(cli.config.animate());
+        assert_eq!(cli.config.effect(), Effect::FadeIn);
+        assert_eq!(Cli::parse(&[]).unwrap().config.effect(), Effect::Shift);
+        let err = Cli::parse(&strings(&["--effect=spin"])).unwrap_err();
+        assert!(err.contains("--effect"), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn parse_animate_forever() {
+        let cli = Cli::parse(&strings(&["--animate=forever"])).unwrap();
+        assert!(cli.config.animate() && cli.config.forever());
+        assert_eq!(cli.config.duration(), 12);
+        let cli = Cli::parse(&strings(&["--animate=2"])).unwrap();
+        assert!(!cli.config.forever());
+        assert!(Cli::parse(&strings(&["--animate=always"])).is_err());
+    }
+
+    #[test]
+    fn parse_record() {
+        let cli = Cli::parse(&strings(&["--record", "out.cast", "--record-only"])).unwrap();
+        assert_eq!(cli.record.as_deref(), Some("out.cast"));
+        assert!(cli.record_only && cli.files.is_empty());
+        let err = Cli::parse(&strings(&["--record-only"])).unwrap_err();
+        assert!(err.contains("--record"), "unexpected error: {err}");
+        let err = Cli::parse(&strings(&[
+            "--record=a.cast",
+            "--record-only",
+            "--animate=forever",
+        ]))
+        .unwrap_err();
+        assert!(err.contains("forever"), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn parse_play() {
+        let cli = Cli::parse(&strings(&["--play=in.cast", "-s", "3"])).unwrap();
+        assert_eq!(cli.play.as_deref(), Some("in.cast"));
+        assert_eq!(cli.speed, Some(3.0));
+        assert_eq!(Cli::parse(&[]).unwrap().speed, None);
+        let err = Cli::parse(&strings(&["--play", "in.cast", "file.txt"])).unwrap_err();
+        assert!(err.contains("--play"), "unexpected error: {err}");
     }
 
     #[test]
     fn animate_option_leaves_non_numeric_arguments() {
-        let cfg = Config::parse(&strings(&["--animate", "foo"])).unwrap();
-        assert!(cfg.animate);
-        assert_eq!(cfg.files, vec!["foo".to_string()]);
+        let cli = Cli::parse(&strings(&["--animate", "foo"])).unwrap();
+        assert!(cli.config.animate());
+        assert_eq!(cli.files, vec!["foo".to_string()]);
     }
 
     #[test]
     fn validate_rejects_small_spread() {
-        let err = Config::parse(&strings(&["--spread=0.01"])).unwrap_err();
+        let err = Cli::parse(&strings(&["--spread=0.01"])).unwrap_err();
         assert!(err.contains("spread"), "unexpected error: {err}");
     }
 
     #[test]
-    fn duration_conversion_rounds_and_bounds() {
-        assert_eq!(float_duration_to_frames(3.2).unwrap(), 3);
-        assert_eq!(float_duration_to_frames(0.15).unwrap(), 1);
-        assert!(float_duration_to_frames(0.05).is_err());
+    fn parse_gradient_and_mode() {
+        let cli = Cli::parse(&strings(&[
+            "--gradient",
+            "#ff0080,rgb(121, 40, 202),navy",
+            "--gradient-mode=mirror",
+        ]))
+        .unwrap();
+        let gradient = cli.config.gradient().expect("gradient missing");
+        assert_eq!(gradient.stops().len(), 3);
+        assert_eq!(cli.config.gradient_mode(), GradientMode::Mirror);
+
+        let err = Cli::parse(&strings(&["--gradient=#zzz"])).unwrap_err();
+        assert!(err.contains("--gradient"), "unexpected error: {err}");
+        let err = Cli::parse(&strings(&["--gradient-mode", "bounce"])).unwrap_err();
+        assert!(err.contains("--gradient-mode"), "unexpected error: {err}");
     }
 
     #[test]
-    fn choose_color_mode_prefers_truecolor_flag() {
-        let mut cfg = Config::default();
-        cfg.truecolor = true;
-        assert!(matches!(
-            choose_color_mode_from(&cfg, None),
-            ColorMode::TrueColor
-        ));
-        cfg.truecolor = false;
-        assert!(matches!(
-            choose_color_mode_from(&cfg, Some("24bit")),
-            ColorMode::TrueColor
-        ));
-        assert!(matches!(
-            choose_color_mode_from(&cfg, Some("ansi")),
-            ColorMode::Ansi256
-        ));
-    }
+    fn parse_theme_sets_gradient() {
+        let cli = Cli::parse(&strings(&["--theme", "fire"])).unwrap();
+        let expected = find_theme("fire").unwrap().gradient();
+        assert_eq!(cli.config.gradient(), Some(&expected));
 
-    #[test]
-    fn detects_truecolor_env_toggle() {
-        assert!(detects_truecolor_from(Some("truecolor")));
-        assert!(detects_truecolor_from(Some("24BIT")));
-        assert!(!detects_truecolor_from(Some("ansi")));
-        assert!(!detects_truecolor_from(None));
+        let err = Cli::parse(&strings(&["--theme=plaid"])).unwrap_err();
+        assert!(err.contains("plaid"), "unexpected error: {err}");
     }
 
     #[test]
-    fn streaming_preserves_escape_sequences() {
-        let cfg = Config {
-            force: true,
-            ..Config::default()
-        };
-        let mut printer = Printer::new(&cfg, true, ColorMode::Ansi256, 0.0);
-        let mut output = Vec::new();
-        let input = b"\x1b[31mhello\nworld";
-        let reader = Chunked::new(&input[..], 2);
+    fn parse_colorspace_controls() {
+        let cli = Cli::parse(&strings(&[
+            "--colorspace=oklch",
+            "--lightness",
+            "0.6",
+            "--chroma=0.1",
+        ]))
+        .unwrap();
+        assert_eq!(cli.config.colorspace(), ColorSpace::Oklch);
+        assert_eq!(cli.config.lightness(), 0.6);
+        assert_eq!(cli.config.chroma(), 0.1);
 
-        process_stream_streaming(reader, &mut output, &mut printer).unwrap();
+        let cli = Cli::parse(&strings(&["--colorspace", "hsl"])).unwrap();
+        assert_eq!(cli.config.lightness(), 0.5);
 
-        assert!(
-            output.windows(5).any(|w| w == b"\x1b[31m"),
-            "escape sequence missing in {:?}",
-            output
-        );
+        let err = Cli::parse(&strings(&["--lightness=2"])).unwrap_err();
+        assert!(err.contains("--lightness"), "unexpected error: {err}");
     }
 
     #[test]
-    fn streaming_replaces_invalid_utf8() {
-        let cfg = Config {
-            force: true,
-            ..Config::default()
-        };
-        let mut printer = Printer::new(&cfg, true, ColorMode::Ansi256, 0.0);
-        let mut output = Vec::new();
-        let input = [0xFF, 0xFF, b'\n'];
-        let reader = Chunked::new(&input, 1);
+    fn parse_colors_mode() {
+        let cli = Cli::parse(&strings(&["--colors", "16"])).unwrap();
+        assert_eq!(cli.config.colors(), Some(ColorMode::Ansi16));
+        let cli = Cli::parse(&strings(&["--colors=8", "-t"])).unwrap();
+        assert_eq!(cli.config.colors(), Some(ColorMode::TrueColor));
+        let err = Cli::parse(&strings(&["--colors=88"])).unwrap_err();
+        assert!(err.contains("--colors"), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn duration_conversion_rounds_and_bounds() {
+        assert_eq!(float_duration_to_frames(3.2).unwrap(), 3);
+        assert_eq!(float_duration_to_frames(0.15).unwrap(), 1);
+        assert!(float_duration_to_frames(0.05).is_err());
+    }
 
-        process_stream_streaming(reader, &mut output, &mut printer).unwrap();
+    #[test]
+    fn parse_background_and_query() {
+        let cli = Cli::parse(&strings(&["--background=light", "--query-terminal"])).unwrap();
+        assert_eq!(cli.config.background(), Some(Background::Light));
+        assert!(cli.query_terminal);
+        let cli = Cli::parse(&[]).unwrap();
+        assert_eq!(cli.config.background(), None);
+        let err = Cli::parse(&strings(&["--background", "grey"])).unwrap_err();
+        assert!(err.contains("--background"), "unexpected error: {err}");
+    }
 
-        assert!(
-            output.windows(3).any(|w| w == &[0xEF, 0xBF, 0xBD]),
-            "replacement char missing in {:?}",
-            output
+    #[test]
+    fn parse_quantize() {
+        let cli = Cli::parse(&strings(&["--quantize", "fast"])).unwrap();
+        assert_eq!(cli.config.quantize(), Quantize::Fast);
+        assert!(!cli.config.dither());
+        let cli = Cli::parse(&strings(&["--dither", "--quantize=accurate"])).unwrap();
+        assert!(cli.config.dither());
+        assert_eq!(
+            Cli::parse(&[]).unwrap().config.quantize(),
+            Quantize::Accurate
         );
+        let err = Cli::parse(&strings(&["--quantize=best"])).unwrap_err();
+        assert!(err.contains("--quantize"), "unexpected error: {err}");
     }
 
     #[test]
-    fn rgb_to_ansi256_maps_primary_colors() {
-        assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
-        assert_eq!(rgb_to_ansi256(0, 255, 0), 46);
-        assert_eq!(rgb_to_ansi256(0, 0, 255), 21);
-        assert_eq!(rgb_to_ansi256(128, 128, 128), 243);
+    fn parse_direction_and_center() {
+        let cli = Cli::parse(&strings(&["--direction=angle=30", "--center", "40, 12.5"])).unwrap();
+        assert_eq!(cli.config.direction(), Direction::Angle(30.0));
+        assert_eq!(cli.config.center(), Some((40.0, 12.5)));
+        let err = Cli::parse(&strings(&["--center=40"])).unwrap_err();
+        assert!(err.contains("--center"), "unexpected error: {err}");
+        let err = Cli::parse(&strings(&["--direction", "up"])).unwrap_err();
+        assert!(err.contains("--direction"), "unexpected error: {err}");
     }
 
-    struct Chunked<'a> {
-        data: &'a [u8],
-        pos: usize,
-        chunk: usize,
+    #[test]
+    fn parse_granularity() {
+        let cli = Cli::parse(&strings(&["--granularity", "word"])).unwrap();
+        assert_eq!(cli.config.granularity(), Granularity::Word);
+        assert_eq!(
+            Cli::parse(&[]).unwrap().config.granularity(),
+            Granularity::Char
+        );
+        let err = Cli::parse(&strings(&["--granularity=glyph"])).unwrap_err();
+        assert!(err.contains("--granularity"), "unexpected error: {err}");
     }
 
-    impl<'a> Chunked<'a> {
-        fn new(data: &'a [u8], chunk: usize) -> Self {
-            Self {
-                data,
-                pos: 0,
-                chunk,
-            }
-        }
+    #[test]
+    fn parse_tabs() {
+        let cli = Cli::parse(&strings(&["--tabs", "4", "--keep-tabs"])).unwrap();
+        assert_eq!(cli.config.tabs(), &TabStops::Every(4));
+        assert!(cli.config.keep_tabs());
+        let cli = Cli::parse(&strings(&["--tabs=2,6,12"])).unwrap();
+        assert_eq!(cli.config.tabs(), &TabStops::List(vec![2, 6, 12]));
+        assert!(!cli.config.keep_tabs());
+        let err = Cli::parse(&strings(&["--tabs=0"])).unwrap_err();
+        assert!(err.contains("--tabs"), "unexpected error: {err}");
     }
 
-    impl Read for Chunked<'_> {
-        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-            if self.pos >= self.data.len() {
-                return Ok(0);
-            }
-            let remaining = self.data.len() - self.pos;
-            let take = remaining.min(self.chunk).min(buf.len());
-            buf[..take].copy_from_slice(&self.data[self.pos..self.pos + take]);
-            self.pos += take;
-            Ok(take)
-        }
+    #[test]
+    fn parse_existing_color() {
+        let cli = Cli::parse(&strings(&["--existing-color", "preserve"])).unwrap();
+        assert_eq!(cli.config.existing_color(), ExistingColor::Preserve);
+        assert_eq!(
+            Cli::parse(&[]).unwrap().config.existing_color(),
+            ExistingColor::Override
+        );
+        let err = Cli::parse(&strings(&["--existing-color=keep"])).unwrap_err();
+        assert!(err.contains("--existing-color"), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn parse_link_style() {
+        let cli = Cli::parse(&strings(&["--link-style=underline"])).unwrap();
+        assert_eq!(cli.config.link_style(), LinkStyle::Underline);
+        let err = Cli::parse(&strings(&["--link-style", "bold"])).unwrap_err();
+        assert!(err.contains("--link-style"), "unexpected error: {err}");
     }
 }
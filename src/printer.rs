@@ -0,0 +1,1515 @@
+use std::{
+    f64::consts::TAU,
+    io::{self, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{
+    ColorSpace, Config, Direction, GradientMode, Granularity,
+    cast::Clock,
+    color::{
+        ColorMode, build_ansi_prefix, build_ansi16_prefix, build_truecolor_prefix, rgb_to_ansi8,
+        rgb_to_ansi16,
+    },
+    effect::{self, Frame, Look},
+    escape::{EscapeParser, Step},
+    grapheme::Segmenter,
+    hyperlink::{LinkScan, LinkStyle},
+    rainbow::{RainbowRot, RainbowState, initial_offset},
+    sgr::{ExistingColor, InputColors},
+    width::{ClusterWidth, text_width},
+};
+
+const RESET: &str = "\x1b[0m";
+const RESET_FG: &str = "\x1b[39m";
+const RESET_BG: &str = "\x1b[49m";
+const SAVE_CURSOR: &str = "\x1b7";
+const RESTORE_CURSOR: &str = "\x1b8";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const PENDING_CAP: usize = 4096;
+// Makes the terminal drop a sequence we gave up on.
+const CANCEL: &[u8] = b"\x18";
+const CLOSE_LINK: &str = "\x1b]8;;\x1b\\";
+const UNDERLINE: &str = "\x1b[4m";
+const NO_UNDERLINE: &str = "\x1b[24m";
+
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+// Rough distance between neighbouring entries of each palette.
+const DITHER_SPREAD_256: f64 = 40.0;
+const DITHER_SPREAD_16: f64 = 96.0;
+const DITHER_SPREAD_8: f64 = 160.0;
+
+/// Rainbow colorizer: feed it text and an output sink, then call
+/// [`finalize`](Printer::finalize) to restore the terminal.
+pub struct Printer {
+    pub(crate) cfg: Config,
+    os: f64,
+    origin: f64,
+    pub(crate) use_color: bool,
+    color_mode: ColorMode,
+    cursor_hidden: bool,
+    /// Terminal width, for how many rows a wrapped line takes.
+    columns: Option<usize>,
+    /// Lines held back for `--animate-block`, and how many end in a newline.
+    block: String,
+    block_len: usize,
+    /// The animation frame being drawn, if any.
+    frame: Option<Frame>,
+    /// Set from outside to end an endless animation.
+    stop: Option<Arc<AtomicBool>>,
+    /// Schedules animation frames.
+    pub(crate) clock: Clock,
+    line_active: bool,
+    row: f64,
+    column: usize,
+    paragraph_row: f64,
+    line_blank: bool,
+    after_space: bool,
+    held: RainbowState,
+    segmenter: Segmenter,
+    cluster_open: bool,
+    /// The open cluster is withheld by the effect; blanks stand in for it.
+    cluster_hidden: bool,
+    cluster_width: ClusterWidth,
+    /// The color prefix last written and still in effect, if any.
+    active_color: Vec<u8>,
+    escape: EscapeParser,
+    /// The CSI sequence being read, held back to inspect SGR.
+    csi: Vec<u8>,
+    input_colors: InputColors,
+    link_scan: LinkScan,
+    /// An OSC 8 that has ended, applied once its terminator is written.
+    link_change: Option<bool>,
+    /// An OSC 8 cut short by ESC, which only ends it if `\` follows.
+    link_at_st: Option<bool>,
+    link_open: bool,
+    /// For solid links, the phase of the link's first cluster.
+    link_phase: Option<RainbowState>,
+    phase: RainbowState,
+    rot: RainbowRot,
+    buffer: SmallBuf,
+}
+
+impl Printer {
+    /// Creates a colorizing printer whose starting phase comes from the config seed.
+    pub fn new(cfg: Config, color_mode: ColorMode) -> Self {
+        let offset = initial_offset(cfg.seed);
+        let angle = cfg.freq * offset;
+        let (col_weight, _) = cfg.direction.weights().unwrap_or_default();
+        let rot = RainbowRot::new(cfg.freq / cfg.spread * col_weight);
+        Self {
+            cfg,
+            os: offset,
+            origin: angle,
+            use_color: true,
+            color_mode,
+            cursor_hidden: false,
+            columns: None,
+            block: String::new(),
+            block_len: 0,
+            frame: None,
+            stop: None,
+            clock: Clock::real(),
+            line_active: false,
+            row: 0.0,
+            column: 0,
+            paragraph_row: 0.0,
+            line_blank: true,
+            after_space: true,
+            held: RainbowState::from_angle(angle),
+            segmenter: Segmenter::new(),
+            cluster_open: false,
+            cluster_hidden: false,
+            cluster_width: ClusterWidth::default(),
+            active_color: Vec::new(),
+            escape: EscapeParser::new(),
+            csi: Vec::new(),
+            input_colors: InputColors::default(),
+            link_scan: LinkScan::default(),
+            link_change: None,
+            link_at_st: None,
+            link_open: false,
+            link_phase: None,
+            phase: RainbowState::from_angle(angle),
+            rot,
+            buffer: SmallBuf::new(),
+        }
+    }
+
+    /// Overrides the starting rainbow offset.
+    pub fn with_offset(mut self, offset: f64) -> Self {
+        self.os = offset;
+        self.origin = self.cfg.freq * offset;
+        self.phase = RainbowState::from_angle(self.origin);
+        self
+    }
+
+    /// With color disabled, input is copied through untouched.
+    pub fn with_color(mut self, use_color: bool) -> Self {
+        self.use_color = use_color;
+        self
+    }
+
+    /// Sets the terminal width block animation wraps lines at; without it
+    /// every line is taken to fit on one row.
+    pub fn with_terminal_width(mut self, columns: Option<usize>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Ends an `--animate=forever` animation once `stop` is set: the frame
+    /// on screen is replaced by the settled one, and any further lines get
+    /// just that single frame.
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Schedules animation frames on `clock`, shared with a
+    /// [`CastWriter`](crate::CastWriter) recording the output.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The palette colors are written in.
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    /// The config the printer was built with.
+    pub fn config(&self) -> &Config {
+        &self.cfg
+    }
+
+    /// Plays any block still held for animation, shows the cursor again,
+    /// closes any hyperlink the input left open, resets all attributes and
+    /// flushes `writer`.
+    pub fn finalize(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        self.animate_block(writer)?;
+        if self.cursor_hidden {
+            self.buffer.push(writer, SHOW_CURSOR.as_bytes())?;
+            self.cursor_hidden = false;
+        }
+        if self.use_color {
+            // The full reset below covers the color too.
+            self.active_color.clear();
+            self.close_cluster(writer)?;
+            self.abandon_escape(writer)?;
+            self.close_link(writer)?;
+            self.buffer.push(writer, RESET.as_bytes())?;
+        }
+        self.buffer.flush(writer)?;
+        writer.flush()
+    }
+
+    /// Closes out one input source: drops any escape sequence or hyperlink
+    /// it left open and resets the attributes it set, so the next source
+    /// starts clean.
+    pub fn end_source(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        self.animate_block(writer)?;
+        if !self.use_color {
+            return Ok(());
+        }
+        self.active_color.clear();
+        self.close_cluster(writer)?;
+        self.abandon_escape(writer)?;
+        self.close_link(writer)?;
+        self.input_colors = InputColors::default();
+        self.buffer.push(writer, RESET.as_bytes())
+    }
+
+    /// Colors `text` line by line, animating each line when the config asks for it.
+    pub fn print_text(&mut self, text: &str, writer: &mut dyn Write) -> io::Result<()> {
+        for line in text.split_inclusive('\n') {
+            let (body, newline) = if let Some(stripped) = line.strip_suffix('\n') {
+                (stripped, true)
+            } else {
+                (line, false)
+            };
+            self.print_line(body, newline, writer)?;
+        }
+        Ok(())
+    }
+
+    /// Colors raw bytes, replacing invalid UTF-8 with U+FFFD.
+    pub fn print_bytes(&mut self, bytes: &[u8], writer: &mut dyn Write) -> io::Result<()> {
+        crate::stream::process_stream(bytes, writer, self).map_err(io::Error::from)
+    }
+
+    /// Colors one line given without its newline, ending it with one when
+    /// `had_newline`.
+    pub fn print_line(
+        &mut self,
+        text: &str,
+        had_newline: bool,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        if self.cfg.animate && self.cfg.animate_block {
+            self.hold_block_line(text, had_newline, writer)
+        } else if self.cfg.animate && !text.is_empty() {
+            self.animate_line(text, had_newline, writer)
+        } else {
+            self.print_plain_line(text, had_newline, writer)
+        }
+    }
+
+    fn animate_line(
+        &mut self,
+        text: &str,
+        had_newline: bool,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.hide_cursor(writer)?;
+        self.buffer.push(writer, SAVE_CURSOR.as_bytes())?;
+        let original = self.os;
+        let total_cells = text_width(text, &self.cfg.tabs);
+        let frame_time = Duration::from_secs_f64(1.0 / self.cfg.speed);
+        let mut next_frame = self.clock.pin();
+        let mut index = 0;
+        loop {
+            self.buffer.push(writer, RESTORE_CURSOR.as_bytes())?;
+            if self.cfg.effect.shifts_hue() {
+                self.os += self.cfg.spread;
+            }
+            let (frame, last) = self.frame_at(index, total_cells);
+            self.frame = Some(frame);
+            let text = self.cfg.effect.frame_text(text, &frame);
+            self.print_plain_line(&text, false, writer)?;
+            self.buffer.flush(writer)?;
+            writer.flush()?;
+            next_frame = self.clock.sleep_until(next_frame + frame_time);
+            if last {
+                break;
+            }
+            index = index.wrapping_add(1);
+        }
+        self.clock.release();
+        self.frame = None;
+        self.os = original;
+        if had_newline {
+            self.buffer.push(writer, b"\n")?;
+            self.end_row();
+        }
+        self.buffer.flush(writer)?;
+        Ok(())
+    }
+
+    /// Adds a line to the block, animating it once it is full.
+    fn hold_block_line(
+        &mut self,
+        text: &str,
+        had_newline: bool,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.block.push_str(text);
+        if had_newline {
+            self.block.push('\n');
+            self.block_len += 1;
+        }
+        if self
+            .cfg
+            .block_lines
+            .is_some_and(|max| self.block_len >= max)
+        {
+            self.animate_block(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Animates every held line at once: each frame redraws the block after
+    /// moving the cursor back up the rows the previous one took.
+    fn animate_block(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+        let block = std::mem::take(&mut self.block);
+        self.block_len = 0;
+        self.hide_cursor(writer)?;
+        let start = (self.os, self.row, self.paragraph_row, self.line_blank);
+        let mut os = self.os;
+        let widths: Vec<usize> = block
+            .split_inclusive('\n')
+            .map(|line| text_width(line, &self.cfg.tabs))
+            .collect();
+        let total_cells = widths.iter().sum();
+        let frame_time = Duration::from_secs_f64(1.0 / self.cfg.speed);
+        let mut next_frame = self.clock.pin();
+        let mut rows = 0;
+        let mut index = 0;
+        loop {
+            if index > 0 {
+                self.buffer.push(writer, b"\r")?;
+                if rows > 0 {
+                    self.buffer
+                        .push(writer, format!("\x1b[{rows}A").as_bytes())?;
+                }
+            }
+            (self.os, self.row, self.paragraph_row, self.line_blank) = start;
+            if self.cfg.effect.shifts_hue() {
+                os += self.cfg.spread;
+            }
+            self.os = os;
+            let (frame, last) = self.frame_at(index, total_cells);
+            rows = self.print_block(&block, &widths, frame, writer)?;
+            self.buffer.flush(writer)?;
+            writer.flush()?;
+            next_frame = self.clock.sleep_until(next_frame + frame_time);
+            if last {
+                break;
+            }
+            index = index.wrapping_add(1);
+        }
+        self.clock.release();
+        self.frame = None;
+        (self.os, self.row, self.paragraph_row, self.line_blank) = start;
+        for _ in 0..block.matches('\n').count() {
+            self.end_row();
+        }
+        Ok(())
+    }
+
+    /// Prints one frame of a block, whose lines take `widths` cells, and
+    /// returns how many rows down the cursor ended up, counting lines that
+    /// wrap at the terminal width.
+    fn print_block(
+        &mut self,
+        block: &str,
+        widths: &[usize],
+        mut frame: Frame,
+        writer: &mut dyn Write,
+    ) -> io::Result<usize> {
+        let mut rows = 0;
+        for (line, width) in block.split_inclusive('\n').zip(widths) {
+            let (body, newline) = match line.strip_suffix('\n') {
+                Some(stripped) => (stripped, true),
+                None => (line, false),
+            };
+            self.frame = Some(frame);
+            frame.cells_before += width;
+            let body = self.cfg.effect.frame_text(body, &frame);
+            self.column = 0;
+            self.print_plain_line(&body, newline, writer)?;
+            let wrapped = match self.columns {
+                Some(columns) if self.column > 0 => self.column.div_ceil(columns),
+                _ => 1,
+            };
+            rows += if newline { wrapped } else { wrapped - 1 };
+        }
+        Ok(rows)
+    }
+
+    /// Frame `index` of an animation over `total_cells` cells, and whether
+    /// it is the last: after `--duration` frames, or once stopped when
+    /// animating forever. Endless effects start over every `--duration`
+    /// frames and finish on the settled frame.
+    fn frame_at(&self, index: u32, total_cells: usize) -> (Frame, bool) {
+        let count = self.cfg.duration;
+        let last = if self.cfg.forever {
+            self.stop
+                .as_ref()
+                .is_some_and(|stop| stop.load(Ordering::Relaxed))
+        } else {
+            index + 1 >= count
+        };
+        let frame = Frame {
+            index: if last { count - 1 } else { index % count },
+            count,
+            cells_before: 0,
+            total_cells,
+        };
+        (frame, last)
+    }
+
+    fn hide_cursor(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        if !self.cursor_hidden {
+            self.buffer.push(writer, HIDE_CURSOR.as_bytes())?;
+            self.cursor_hidden = true;
+        }
+        Ok(())
+    }
+
+    fn print_plain_line(
+        &mut self,
+        text: &str,
+        had_newline: bool,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        if !self.use_color {
+            self.buffer.flush(writer)?;
+            writer.write_all(text.as_bytes())?;
+            if had_newline {
+                writer.write_all(b"\n")?;
+            }
+            return Ok(());
+        }
+
+        self.line_active = false;
+        self.write_plain_segment(text, writer)?;
+        if had_newline {
+            self.finish_line(writer)?;
+        } else {
+            self.close_cluster(writer)?;
+            self.line_active = false;
+        }
+        self.abandon_escape(writer)
+    }
+
+    pub(crate) fn write_plain_segment(
+        &mut self,
+        text: &str,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        debug_assert!(self.use_color);
+        for ch in text.chars() {
+            if let Some(opens) = self.link_at_st.take()
+                && ch == '\\'
+            {
+                self.link_change = Some(opens);
+            }
+            let was_osc = self.escape.in_osc();
+            match self.escape.advance(ch) {
+                Step::Text => {}
+                Step::Begin => {
+                    // ESC ends an OSC; it counts as ST only if `\` follows.
+                    if was_osc && ch == '\x1b' {
+                        self.link_at_st = self.link_scan.opens_link();
+                    }
+                    self.link_scan = LinkScan::default();
+                    self.begin_escape(ch, writer)?;
+                    continue;
+                }
+                step @ (Step::Continue | Step::End) => {
+                    if was_osc {
+                        match ch {
+                            '\x07' | '\u{9c}' => self.link_change = self.link_scan.opens_link(),
+                            _ if step == Step::Continue => self.link_scan.feed(ch),
+                            _ => {}
+                        }
+                    }
+                    self.feed_escape(ch, writer)?;
+                    if !self.escape.is_active() {
+                        self.apply_link_change(writer)?;
+                    }
+                    continue;
+                }
+                Step::Abandon => {
+                    self.release_csi(writer)?;
+                    self.buffer.push(writer, CANCEL)?;
+                    self.apply_link_change(writer)?;
+                }
+            }
+            if ch == '\t' {
+                self.write_tab(writer)?;
+            } else if ch.is_control() {
+                self.write_control(ch, writer)?;
+            } else {
+                self.write_visible_char(ch, writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves to the next tab stop, as colored spaces or as the raw tab.
+    fn write_tab(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        self.ensure_line_active();
+        self.segmenter.reset();
+        self.end_cluster(writer)?;
+        let cells = self.cfg.tabs.next_stop(self.column) - self.column;
+        if !self.cfg.keep_tabs {
+            for _ in 0..cells {
+                self.write_visible_char(' ', writer)?;
+            }
+            return Ok(());
+        }
+        if self.cfg.granularity == Granularity::Word {
+            self.held = self.phase;
+        }
+        self.after_space = true;
+        self.reset_color(writer)?;
+        self.buffer.push(writer, b"\t")?;
+        self.advance_cells(cells);
+        Ok(())
+    }
+
+    /// Passes a C0 or C1 control through uncolored, following the cursor for
+    /// carriage return and backspace so redrawn cells get the same colors.
+    fn write_control(&mut self, ch: char, writer: &mut dyn Write) -> io::Result<()> {
+        self.ensure_line_active();
+        self.close_cluster(writer)?;
+        let mut buf = [0u8; 4];
+        self.buffer
+            .push(writer, ch.encode_utf8(&mut buf).as_bytes())?;
+        match ch {
+            '\r' => self.move_to_column(0),
+            '\x08' => self.move_to_column(self.column.saturating_sub(1)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn write_visible_char(&mut self, ch: char, writer: &mut dyn Write) -> io::Result<()> {
+        self.ensure_line_active();
+        let encoded = &mut [0u8; 4];
+        let glyph = ch.encode_utf8(encoded);
+        // Marks, joiners and the like take the color of the cluster they extend.
+        if !self.segmenter.starts_cluster(ch) {
+            self.cluster_width.push(ch);
+            if self.cluster_hidden {
+                return Ok(());
+            }
+            return self.buffer.push(writer, glyph.as_bytes());
+        }
+        self.end_cluster(writer)?;
+        let space = ch.is_whitespace();
+        if self.cfg.granularity == Granularity::Word && (space || self.after_space) {
+            self.held = self.phase;
+        }
+        self.after_space = space;
+        self.line_blank &= space;
+        self.cluster_open = true;
+        self.cluster_width = ClusterWidth::default();
+        self.cluster_width.push(ch);
+        let shade = match self.look() {
+            Look::Shade(shade) => shade,
+            Look::Hidden => {
+                self.cluster_hidden = true;
+                return self.reset_color(writer);
+            }
+        };
+        let preserved = match self.cfg.existing_color {
+            ExistingColor::Preserve if self.cfg.invert => self.input_colors.bg,
+            ExistingColor::Preserve => self.input_colors.fg,
+            ExistingColor::Override | ExistingColor::Strip => false,
+        };
+        if preserved {
+            self.reset_color(writer)?;
+            return self.buffer.push(writer, glyph.as_bytes());
+        }
+        if self.link_open && self.link_phase.is_none() {
+            self.link_phase = Some(self.color_phase());
+        }
+        let mut block = [0u8; 64];
+        let len = match (self.cfg.invert, self.color_mode) {
+            (invert, ColorMode::TrueColor) => {
+                let (r, g, b) = effect::shade(self.current_color(), shade);
+                build_truecolor_prefix(&mut block, invert, r, g, b)
+            }
+            (invert, ColorMode::Ansi256) => {
+                let idx = self.current_ansi256(shade);
+                build_ansi_prefix(&mut block, invert, idx)
+            }
+            (invert, ColorMode::Ansi16) => {
+                let rgb = effect::shade(self.current_color(), shade);
+                let (r, g, b) = self.dithered(rgb, DITHER_SPREAD_16);
+                build_ansi16_prefix(&mut block, invert, rgb_to_ansi16(r, g, b))
+            }
+            (invert, ColorMode::Ansi8) => {
+                let rgb = effect::shade(self.current_color(), shade);
+                let (r, g, b) = self.dithered(rgb, DITHER_SPREAD_8);
+                build_ansi16_prefix(&mut block, invert, rgb_to_ansi8(r, g, b))
+            }
+        };
+        // A run of one color needs its prefix only once.
+        let start = if block[..len] == self.active_color[..] {
+            len
+        } else {
+            self.active_color.clear();
+            self.active_color.extend_from_slice(&block[..len]);
+            0
+        };
+        block[len..len + glyph.len()].copy_from_slice(glyph.as_bytes());
+        self.buffer.push(writer, &block[start..len + glyph.len()])
+    }
+
+    /// Ends the current cluster and the colored run, so what follows starts
+    /// uncolored.
+    fn close_cluster(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        self.segmenter.reset();
+        self.end_cluster(writer)?;
+        self.reset_color(writer)
+    }
+
+    /// Moves the hue on by the cells the cluster just written took, first
+    /// blanking them if the effect withheld it.
+    fn end_cluster(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        if !self.cluster_open {
+            return Ok(());
+        }
+        self.cluster_open = false;
+        let cells = self.cluster_width.cells();
+        if self.cluster_hidden {
+            self.cluster_hidden = false;
+            for _ in 0..cells {
+                self.buffer.push(writer, b" ")?;
+            }
+        }
+        self.advance_cells(cells);
+        Ok(())
+    }
+
+    /// How the animation effect shows the cell at the cursor.
+    fn look(&self) -> Look {
+        let Some(frame) = &self.frame else {
+            return Look::Shade(1.0);
+        };
+        // Without a seed the random starting offset stands in for one.
+        let seed = match self.cfg.seed {
+            0 => self.origin.to_bits(),
+            seed => seed,
+        };
+        self.cfg.effect.look(frame, self.column, self.row, seed)
+    }
+
+    /// Ends the colored run, if any.
+    fn reset_color(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        if self.active_color.is_empty() {
+            return Ok(());
+        }
+        self.active_color.clear();
+        let reset = if self.cfg.invert { RESET_BG } else { RESET_FG };
+        self.buffer.push(writer, reset.as_bytes())
+    }
+
+    /// Rewinds the phase to the start of the line and steps it forward
+    /// again, so it matches the first pass over `column` exactly.
+    fn move_to_column(&mut self, column: usize) {
+        self.column = 0;
+        self.after_space = true;
+        self.phase.reset(self.line_start_angle());
+        self.advance_cells(column);
+    }
+
+    fn advance_cells(&mut self, cells: usize) {
+        self.column += cells;
+        if self.cfg.direction == Direction::Radial {
+            self.phase.reset(self.radial_angle());
+        } else {
+            for _ in 0..cells {
+                self.phase.advance(self.rot);
+            }
+        }
+    }
+
+    fn current_color(&self) -> (u8, u8, u8) {
+        match &self.cfg.gradient {
+            Some(gradient) => gradient.sample(self.gradient_turns(), self.cfg.gradient_mode),
+            None => match self.cfg.colorspace {
+                ColorSpace::Sine => self
+                    .color_phase()
+                    .channels(self.cfg.background.unwrap_or_default()),
+                space => space.channels(
+                    self.color_phase().angle(),
+                    self.cfg.lightness(),
+                    self.cfg.chroma(),
+                ),
+            },
+        }
+    }
+
+    fn current_ansi256(&self, shade: f64) -> u8 {
+        self.cfg
+            .gradient
+            .as_ref()
+            .filter(|_| shade == 1.0)
+            .and_then(|gradient| {
+                gradient.sample_ansi256_biased(
+                    self.gradient_turns(),
+                    self.cfg.gradient_mode,
+                    self.dither_threshold(),
+                )
+            })
+            .unwrap_or_else(|| {
+                let rgb = effect::shade(self.current_color(), shade);
+                let (r, g, b) = self.dithered(rgb, DITHER_SPREAD_256);
+                self.cfg.quantize.ansi256(r, g, b)
+            })
+    }
+
+    /// Ordered-dither offset in `-0.5..0.5` for the current cell, or 0 when
+    /// dithering is off.
+    fn dither_threshold(&self) -> f64 {
+        if !self.cfg.dither {
+            return 0.0;
+        }
+        let row = self.row as usize % 4;
+        let level = BAYER_4X4[row][self.column % 4];
+        (level as f64 + 0.5) / 16.0 - 0.5
+    }
+
+    /// Nudges every channel by the dither threshold, scaled to roughly the
+    /// gap between neighbouring palette entries.
+    fn dithered(&self, (r, g, b): (u8, u8, u8), spread: f64) -> (u8, u8, u8) {
+        let offset = self.dither_threshold() * spread;
+        if offset == 0.0 {
+            return (r, g, b);
+        }
+        let nudge = |c: u8| (c as f64 + offset).round().clamp(0.0, 255.0) as u8;
+        (nudge(r), nudge(g), nudge(b))
+    }
+
+    /// The phase colors are sampled at: the running one per character, or
+    /// the one held since the current word, line or paragraph began; inside
+    /// a hyperlink, as the link style says.
+    fn color_phase(&self) -> RainbowState {
+        let phase = match self.cfg.granularity {
+            Granularity::Char => self.phase,
+            Granularity::Word | Granularity::Line | Granularity::Paragraph => self.held,
+        };
+        if !self.link_open {
+            return phase;
+        }
+        match self.cfg.link_style {
+            LinkStyle::Solid => self.link_phase.unwrap_or(phase),
+            LinkStyle::Complement => phase.opposite(),
+            LinkStyle::Rainbow | LinkStyle::Underline => phase,
+        }
+    }
+
+    fn gradient_turns(&self) -> f64 {
+        let angle = self.color_phase().angle();
+        let angle = match self.cfg.gradient_mode {
+            GradientMode::Clamp => angle - self.origin,
+            GradientMode::Cycle | GradientMode::Mirror => angle,
+        };
+        angle / TAU
+    }
+
+    pub(crate) fn finish_line(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        self.close_cluster(writer)?;
+        self.buffer.push(writer, b"\n")?;
+        self.end_row();
+        self.line_active = false;
+        Ok(())
+    }
+
+    fn end_row(&mut self) {
+        self.os += 1.0;
+        self.row += 1.0;
+        if self.line_blank {
+            self.paragraph_row = self.row;
+        }
+        self.line_blank = true;
+    }
+
+    fn ensure_line_active(&mut self) {
+        if !self.line_active {
+            self.line_active = true;
+            self.column = 0;
+            self.after_space = true;
+            self.phase.reset(self.line_start_angle());
+            self.held = self.phase;
+        }
+    }
+
+    /// Phase at the start of the current row; radial gradients read the
+    /// column, so callers reset it to 0 first.
+    fn line_start_angle(&self) -> f64 {
+        // Paragraphs take their color from their first row.
+        let row = match self.cfg.granularity {
+            Granularity::Paragraph => self.paragraph_row,
+            _ => self.row,
+        };
+        match self.cfg.direction.weights() {
+            // `os` already carries one step per row.
+            Some((_, row_weight)) => self.cfg.freq * (self.os - self.row + row_weight * row),
+            None => self.radial_angle(),
+        }
+    }
+
+    /// Phase at the current cell for radial gradients: the offset plus the
+    /// distance from the center to the middle of the cell, with columns
+    /// scaled by the spread.
+    fn radial_angle(&self) -> f64 {
+        let (center_col, center_row) = self.cfg.center.unwrap_or_default();
+        let dx = (self.column as f64 + 0.5 - center_col) / self.cfg.spread;
+        let dy = self.row + 0.5 - center_row;
+        self.cfg.freq * (self.os - self.row + dx.hypot(dy))
+    }
+
+    fn begin_escape(&mut self, ch: char, writer: &mut dyn Write) -> io::Result<()> {
+        self.close_cluster(writer)?;
+        self.release_csi(writer)?;
+        let mut buf = [0u8; 4];
+        let encoded = ch.encode_utf8(&mut buf);
+        // ESC may turn out to start a CSI sequence.
+        if self.inspects_sgr() && (ch == '\x1b' || self.escape.in_csi()) {
+            self.csi.extend_from_slice(encoded.as_bytes());
+            return Ok(());
+        }
+        self.buffer.push(writer, encoded.as_bytes())
+    }
+
+    fn feed_escape(&mut self, ch: char, writer: &mut dyn Write) -> io::Result<()> {
+        let mut buf = [0u8; 4];
+        let encoded = ch.encode_utf8(&mut buf);
+        if self.csi.is_empty() {
+            return self.buffer.push(writer, encoded.as_bytes());
+        }
+        self.csi.extend_from_slice(encoded.as_bytes());
+        if !self.escape.is_active() {
+            self.finish_csi(writer)
+        } else if self.escape.in_csi() {
+            Ok(())
+        } else {
+            self.release_csi(writer)
+        }
+    }
+
+    /// Drops an unfinished sequence, telling the terminal to do the same.
+    fn abandon_escape(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        if !self.escape.is_active() {
+            return Ok(());
+        }
+        self.escape.reset();
+        self.link_at_st = None;
+        self.release_csi(writer)?;
+        self.buffer.push(writer, CANCEL)?;
+        self.apply_link_change(writer)
+    }
+
+    /// Follows a finished OSC 8 into or out of a hyperlink.
+    fn apply_link_change(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        let Some(opens) = self.link_change.take() else {
+            return Ok(());
+        };
+        let underline = self.cfg.link_style == LinkStyle::Underline;
+        if opens {
+            // A link opened inside another replaces it.
+            self.link_phase = None;
+            if underline && !self.link_open {
+                self.buffer.push(writer, UNDERLINE.as_bytes())?;
+            }
+        } else if underline && self.link_open {
+            self.buffer.push(writer, NO_UNDERLINE.as_bytes())?;
+        }
+        self.link_open = opens;
+        Ok(())
+    }
+
+    /// Closes a hyperlink the input never closed.
+    fn close_link(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        if !self.link_open {
+            return Ok(());
+        }
+        self.link_open = false;
+        self.link_phase = None;
+        if self.cfg.link_style == LinkStyle::Underline {
+            self.buffer.push(writer, NO_UNDERLINE.as_bytes())?;
+        }
+        self.buffer.push(writer, CLOSE_LINK.as_bytes())
+    }
+
+    /// Handles a complete CSI sequence: SGR is dropped when stripping and
+    /// tracked when preserving; everything else passes through.
+    fn finish_csi(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        let sgr = self
+            .csi
+            .strip_prefix(b"\x1b[")
+            .or_else(|| self.csi.strip_prefix("\u{9b}".as_bytes()))
+            .and_then(|rest| rest.strip_suffix(b"m"))
+            .filter(|params| {
+                params
+                    .iter()
+                    .all(|b| b.is_ascii_digit() || b";:".contains(b))
+            });
+        if let Some(params) = sgr {
+            match self.cfg.existing_color {
+                ExistingColor::Strip => {
+                    self.csi.clear();
+                    return Ok(());
+                }
+                ExistingColor::Preserve => self.input_colors.apply(params),
+                ExistingColor::Override => {}
+            }
+        }
+        self.release_csi(writer)
+    }
+
+    /// Whether CSI sequences are held back until complete to look for SGR.
+    fn inspects_sgr(&self) -> bool {
+        self.cfg.existing_color != ExistingColor::Override
+    }
+
+    /// Writes out whatever part of a CSI sequence is being held back.
+    fn release_csi(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        self.buffer.push(writer, &self.csi)?;
+        self.csi.clear();
+        Ok(())
+    }
+
+    pub(crate) fn write_replacement(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        self.write_visible_char('\u{FFFD}', writer)
+    }
+
+    pub(crate) fn flush_pending(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        self.close_cluster(writer)?;
+        self.buffer.flush(writer)
+    }
+}
+
+struct SmallBuf {
+    data: [u8; PENDING_CAP],
+    len: usize,
+}
+
+impl SmallBuf {
+    fn new() -> Self {
+        Self {
+            data: [0u8; PENDING_CAP],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, writer: &mut dyn Write, chunk: &[u8]) -> io::Result<()> {
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        if chunk.len() >= self.data.len() {
+            self.flush(writer)?;
+            return writer.write_all(chunk);
+        }
+        if self.len + chunk.len() > self.data.len() {
+            self.flush(writer)?;
+        }
+        self.data[self.len..self.len + chunk.len()].copy_from_slice(chunk);
+        self.len += chunk.len();
+        Ok(())
+    }
+
+    /// Empties the buffer even when the write fails, so a writer that took
+    /// part of it (a recording next to a closed stdout) never sees it twice.
+    fn flush(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        let len = std::mem::take(&mut self.len);
+        if len > 0 {
+            writer.write_all(&self.data[..len])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConfigBuilder, Effect, ExistingColor, Gradient, LinkStyle, TabStops};
+    use std::thread;
+
+    #[test]
+    fn print_text_wraps_glyphs_in_color() {
+        let cfg = Config::builder().seed(1).build().unwrap();
+        let mut printer = Printer::new(cfg, ColorMode::TrueColor);
+        let mut output = Vec::new();
+        printer.print_text("hi\n", &mut output).unwrap();
+        printer.finalize(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with("\x1b[38;2;"), "unexpected output {text:?}");
+        assert!(text.ends_with(&format!("\n{RESET}")));
+    }
+
+    #[test]
+    fn dither_alternates_between_neighbouring_entries() {
+        let flat = |dither| {
+            let cfg = Config::builder()
+                .gradient(Gradient::new(vec![(115, 115, 40)]).unwrap())
+                .dither(dither)
+                .build()
+                .unwrap();
+            let mut printer = Printer::new(cfg, ColorMode::Ansi256);
+            let mut output = Vec::new();
+            printer.print_text("xxxxxxxx\n", &mut output).unwrap();
+            printer.finalize(&mut output).unwrap();
+            let text = String::from_utf8(output).unwrap();
+            let mut codes: Vec<_> = text
+                .split("38;5;")
+                .skip(1)
+                .map(|s| s[..3].to_string())
+                .collect();
+            codes.sort();
+            codes.dedup();
+            codes.len()
+        };
+        assert_eq!(flat(false), 1);
+        assert!(flat(true) > 1);
+    }
+
+    #[test]
+    fn directions_follow_rows_and_columns() {
+        let codes = |direction| {
+            let cfg = Config::builder()
+                .direction(direction)
+                .center((2.5, 1.5))
+                .freq(0.5)
+                .seed(1)
+                .build()
+                .unwrap();
+            let mut printer = Printer::new(cfg, ColorMode::TrueColor);
+            let mut output = Vec::new();
+            printer
+                .print_text("xxxxx\nxxxxx\nxxxxx\n", &mut output)
+                .unwrap();
+            printer.finalize(&mut output).unwrap();
+            decode_cells(&String::from_utf8(output).unwrap())
+        };
+        let vertical = codes(Direction::Vertical);
+        assert!(
+            vertical
+                .iter()
+                .all(|line| line.iter().all(|c| *c == line[0]))
+        );
+        assert_ne!(vertical[0][0], vertical[1][0]);
+
+        let horizontal = codes(Direction::Horizontal);
+        assert_eq!(horizontal[0], horizontal[2]);
+        assert_ne!(horizontal[0][0], horizontal[0][1]);
+
+        // Radial rings are symmetric around the center cell.
+        let radial = codes(Direction::Radial);
+        assert_eq!(radial[0], radial[2]);
+        assert_eq!(radial[1][1], radial[1][3]);
+        assert_ne!(radial[1][2], radial[1][3]);
+    }
+
+    #[test]
+    fn granularity_holds_colors() {
+        let lines = |granularity| {
+            let cfg = Config::builder()
+                .granularity(granularity)
+                .freq(0.5)
+                .seed(1)
+                .build()
+                .unwrap();
+            let mut printer = Printer::new(cfg, ColorMode::TrueColor);
+            let mut output = Vec::new();
+            printer
+                .print_text("ab cd\nef gh\n\nij kl\n", &mut output)
+                .unwrap();
+            printer.finalize(&mut output).unwrap();
+            decode_cells(&String::from_utf8(output).unwrap())
+        };
+        let words = lines(Granularity::Word);
+        assert_eq!(words[0][0], words[0][1]);
+        assert_eq!(words[0][3], words[0][4]);
+        assert_ne!(words[0][1], words[0][3]);
+
+        let solid = lines(Granularity::Line);
+        assert!(solid[0].iter().all(|c| *c == solid[0][0]));
+        assert_ne!(solid[0][0], solid[1][0]);
+
+        let paragraphs = lines(Granularity::Paragraph);
+        assert!(paragraphs[1].iter().all(|c| *c == paragraphs[0][0]));
+        assert!(paragraphs[2].is_empty());
+        assert_ne!(paragraphs[3][0], paragraphs[0][0]);
+    }
+
+    #[test]
+    fn grapheme_clusters_share_one_color() {
+        let cfg = Config::builder().seed(1).build().unwrap();
+        let mut printer = Printer::new(cfg, ColorMode::TrueColor);
+        let mut output = Vec::new();
+        let text = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}e\u{301}\u{1100}\u{1161}\u{11A8}\n";
+        printer.print_text(text, &mut output).unwrap();
+        printer.finalize(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(text.matches("38;2;").count(), 3, "{text:?}");
+        assert!(text.contains("e\u{301}\x1b[38;2;"));
+    }
+
+    #[test]
+    fn runs_of_one_color_share_a_prefix() {
+        let lines = |invert| {
+            let cfg = Config::builder()
+                .granularity(Granularity::Line)
+                .invert(invert)
+                .build()
+                .unwrap();
+            render(Printer::new(cfg, ColorMode::Ansi256), "ab cd\n\x1b[1mx\n")
+        };
+        let text = lines(false);
+        assert_eq!(text.matches("38;5;").count(), 2, "{text:?}");
+        assert!(
+            text.contains("mab cd\x1b[39m\n\x1b[1m\x1b[38;5;"),
+            "{text:?}"
+        );
+        assert!(text.ends_with("mx\x1b[39m\n\x1b[0m"), "{text:?}");
+        let text = lines(true);
+        assert_eq!(text.matches("48;5;").count(), 2, "{text:?}");
+        assert_eq!(text.matches(RESET_BG).count(), 2, "{text:?}");
+    }
+
+    /// Replays `output` the way a terminal would, line by line: the color
+    /// parameters (`r;g;b` or the index) in effect for every colored
+    /// character.
+    fn decode_cells(output: &str) -> Vec<Vec<String>> {
+        let mut lines = vec![Vec::new()];
+        let mut color: Option<String> = None;
+        let mut chars = output.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1b' => match chars.next() {
+                    Some('[') => {
+                        let sequence: String = chars
+                            .by_ref()
+                            .take_while(|ch| !('@'..='~').contains(ch))
+                            .collect();
+                        if let Some(params) = ["38;2;", "38;5;", "48;2;", "48;5;"]
+                            .iter()
+                            .find_map(|prefix| sequence.strip_prefix(prefix))
+                        {
+                            color = Some(params.to_string());
+                        } else if ["", "0", "39", "49"].contains(&sequence.as_str()) {
+                            color = None;
+                        }
+                    }
+                    // OSC, up to BEL or ST.
+                    Some(']') => {
+                        while let Some(ch) = chars.next() {
+                            if ch == '\x07' || ch == '\x1b' && chars.next() == Some('\\') {
+                                break;
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                '\n' => lines.push(Vec::new()),
+                _ if ch.is_control() => {}
+                _ => {
+                    if let Some(color) = &color {
+                        lines.last_mut().unwrap().push(color.clone());
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    /// The color of every colored character in the output for `text`.
+    fn cell_colors(cfg: &mut ConfigBuilder, text: &str) -> Vec<String> {
+        let mut printer = Printer::new(cfg.seed(1).build().unwrap(), ColorMode::TrueColor);
+        let mut output = Vec::new();
+        printer.print_text(text, &mut output).unwrap();
+        printer.finalize(&mut output).unwrap();
+        decode_cells(&String::from_utf8(output).unwrap()).concat()
+    }
+
+    /// Everything `printer` writes for `text`, finalized as at the end of a
+    /// run.
+    fn render(printer: Printer, text: &str) -> String {
+        run(printer, |printer, output| printer.print_text(text, output))
+    }
+
+    /// [`render`] for raw bytes, through the stream path.
+    fn render_bytes(printer: Printer, bytes: &[u8]) -> String {
+        run(printer, |printer, output| {
+            printer.print_bytes(bytes, output)
+        })
+    }
+
+    fn run(
+        mut printer: Printer,
+        print: impl FnOnce(&mut Printer, &mut Vec<u8>) -> io::Result<()>,
+    ) -> String {
+        let mut output = Vec::new();
+        print(&mut printer, &mut output).unwrap();
+        printer.finalize(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn hue_advances_by_cells() {
+        let colors = |text| cell_colors(&mut Config::builder(), text);
+        // A wide character takes two steps, a zero-width one none.
+        assert_eq!(colors("\u{65E5}a\n")[1], colors("xxa\n")[2]);
+        assert_eq!(colors("\u{200B}a\n")[1], colors("a\n")[0]);
+    }
+
+    #[test]
+    fn tabs_expand_to_the_next_stop() {
+        let plain = cell_colors(&mut Config::builder(), "xxxxxxxxxxxc\n");
+        let expanded = cell_colors(&mut Config::builder(), "ab\tc\n");
+        assert_eq!(expanded.len(), 9);
+        assert_eq!(expanded[8], plain[8]);
+
+        let stops = cell_colors(
+            Config::builder().tabs(TabStops::List(vec![3, 5])),
+            "a\tb\tc\n",
+        );
+        assert_eq!(stops[3], plain[3]);
+        assert_eq!(stops[5], plain[5]);
+
+        let mut printer = Printer::new(
+            Config::builder().seed(1).keep_tabs(true).build().unwrap(),
+            ColorMode::TrueColor,
+        );
+        let mut output = Vec::new();
+        printer.print_text("ab\tc\n", &mut output).unwrap();
+        printer.finalize(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains("\x1b[39m\t\x1b[38;2;"), "{text:?}");
+        assert!(text.contains(&format!("{}mc", plain[8])));
+    }
+
+    #[test]
+    fn controls_pass_through_and_move_the_cursor() {
+        let colors = |text| cell_colors(&mut Config::builder(), text);
+        // A progress bar redrawn with `\r` repeats its colors.
+        let redrawn = colors("10%\r20%\n");
+        assert_eq!(redrawn[..3], redrawn[3..]);
+        let backspaced = colors("ab\x08c\n");
+        assert_eq!(backspaced[2], backspaced[1]);
+        assert_eq!(colors("a\x07b\n"), colors("ab\n"));
+
+        let mut printer = Printer::new(Config::default(), ColorMode::Ansi256);
+        let mut output = Vec::new();
+        printer.print_text("a\x07\r\n", &mut output).unwrap();
+        printer.finalize(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.ends_with("\x1b[39m\x07\r\n\x1b[0m"), "{text:?}");
+    }
+
+    #[test]
+    fn existing_colors_are_overridden_preserved_or_stripped() {
+        let handled = |mode| {
+            let cfg = Config::builder().existing_color(mode).build().unwrap();
+            render_bytes(
+                Printer::new(cfg, ColorMode::Ansi256),
+                b"a\x1b[01;31mbc\x1b[0md\x1b[?25h\n",
+            )
+        };
+        let colored = |text: &str| decode_cells(text).concat().len();
+        let overridden = handled(ExistingColor::Override);
+        assert_eq!(colored(&overridden), 4);
+        assert!(overridden.contains("\x1b[01;31m"));
+
+        let preserved = handled(ExistingColor::Preserve);
+        assert_eq!(colored(&preserved), 2);
+        assert!(preserved.contains("\x1b[01;31mbc\x1b[0m\x1b[38;5;"));
+
+        let stripped = handled(ExistingColor::Strip);
+        assert_eq!(colored(&stripped), 4);
+        assert!(!stripped.contains("31m") && !stripped.contains("\x1b[0md"));
+        assert!(stripped.contains("\x1b[?25h"));
+    }
+
+    #[test]
+    fn unterminated_and_eight_bit_sequences() {
+        let parsed = |input: &[u8], mode| {
+            let cfg = Config::builder().existing_color(mode).build().unwrap();
+            render_bytes(Printer::new(cfg, ColorMode::Ansi256), input)
+        };
+        // An OSC that never ends is cut off, and the text after it colored.
+        let mut runaway = b"\x1b]0;".to_vec();
+        runaway.extend(std::iter::repeat_n(b'a', 70_000));
+        runaway.extend_from_slice(b"\nok\n");
+        let text = parsed(&runaway, ExistingColor::Override);
+        let (swallowed, rest) = text.split_once('\x18').unwrap();
+        assert!(swallowed.len() < 65_600 && !swallowed.contains("38;5;"));
+        assert_eq!(decode_cells(rest)[1].len(), 2);
+
+        let text = parsed("\u{9b}31mab\u{9b}0m".as_bytes(), ExistingColor::Strip);
+        assert!(!text.contains("31m") && decode_cells(&text)[0].len() == 2);
+        let text = parsed("\u{9b}31mab".as_bytes(), ExistingColor::Preserve);
+        assert!(text.starts_with("\u{9b}31mab"), "{text:?}");
+
+        // Truncated input gets a CAN before the final reset.
+        let text = parsed(b"x\x1b[38;5", ExistingColor::Preserve);
+        assert!(text.ends_with("\x1b[38;5\x18\x1b[0m"), "{text:?}");
+    }
+
+    #[test]
+    fn hyperlinks_are_tracked_and_styled() {
+        let linked = "a\x1b]8;;https://example.com\x1b\\bcd\x1b]8;;\x07e\n";
+        let plain = cell_colors(&mut Config::builder(), "abcde\n");
+        let styled = |style| cell_colors(Config::builder().link_style(style), linked);
+        assert_eq!(styled(LinkStyle::Rainbow), plain);
+        assert_eq!(styled(LinkStyle::Underline), plain);
+
+        let solid = styled(LinkStyle::Solid);
+        assert!(solid[1..4].iter().all(|color| *color == plain[1]));
+        assert_eq!(solid[4], plain[4]);
+        let complement = styled(LinkStyle::Complement);
+        assert!((1..4).all(|idx| complement[idx] != plain[idx]));
+        assert_eq!(complement[4], plain[4]);
+        // Other OSC strings are not links.
+        let titled = cell_colors(
+            Config::builder().link_style(LinkStyle::Solid),
+            "a\x1b]0;bcd\x07bcde\n",
+        );
+        assert_eq!(titled, plain);
+
+        let underlined = |input: &str| {
+            let cfg = Config::builder()
+                .link_style(LinkStyle::Underline)
+                .build()
+                .unwrap();
+            render(Printer::new(cfg, ColorMode::Ansi256), input)
+        };
+        let text = underlined(linked);
+        assert!(
+            text.contains("example.com\x1b\\\x1b[4m\x1b[38;5;"),
+            "{text:?}"
+        );
+        assert!(text.contains("\x1b]8;;\x07\x1b[24m"), "{text:?}");
+        // A link left open is closed before the final reset.
+        let text = underlined("\x1b]8;;file:///tmp\x07tmp");
+        assert!(text.ends_with("\x1b[24m\x1b]8;;\x1b\\\x1b[0m"), "{text:?}");
+        // ESC without `\` is no ST, so no link opens.
+        let text = underlined("\x1b]8;;https://example.com\x1b[1mab");
+        assert!(!text.contains("\x1b[4m"), "{text:?}");
+    }
+
+    #[test]
+    fn block_animation_redraws_every_line_per_frame() {
+        let animated = |lines: Option<usize>, columns, text: &str| {
+            let cfg = Config::builder()
+                .animate(true)
+                .animate_block(true)
+                .block_lines(lines)
+                .duration(3)
+                .speed(1000.0)
+                .build()
+                .unwrap();
+            render(
+                Printer::new(cfg, ColorMode::Ansi256).with_terminal_width(columns),
+                text,
+            )
+        };
+        // Six cells wrap onto two rows of four.
+        let text = animated(None, Some(4), "abcdef\nxy\n");
+        assert_eq!(text.matches("\r\x1b[3A").count(), 2, "{text:?}");
+        assert_eq!(text.matches('f').count(), 3);
+        assert!(!text.contains(SAVE_CURSOR));
+        assert!(text.starts_with(HIDE_CURSOR) && text.contains(SHOW_CURSOR));
+
+        let text = animated(Some(1), None, "ab\ncd\n");
+        assert_eq!(text.matches("\r\x1b[1A").count(), 4, "{text:?}");
+        let text = animated(None, None, "ab");
+        assert_eq!(text.matches('\r').count(), 2, "{text:?}");
+        assert!(!text.contains('A'));
+    }
+
+    #[test]
+    fn endless_animation_runs_until_stopped() {
+        let endless = |effect, delay: Option<Duration>| {
+            let cfg = Config::builder()
+                .seed(1)
+                .animate(true)
+                .forever(true)
+                .effect(effect)
+                .duration(2)
+                .speed(1000.0)
+                .build()
+                .unwrap();
+            let stop = Arc::new(AtomicBool::new(delay.is_none()));
+            let printer = Printer::new(cfg, ColorMode::TrueColor).with_stop(Arc::clone(&stop));
+            let stopper = delay.map(|delay| {
+                thread::spawn(move || {
+                    thread::sleep(delay);
+                    stop.store(true, Ordering::Relaxed);
+                })
+            });
+            let text = render(printer, "ab\ncd\n");
+            if let Some(stopper) = stopper {
+                stopper.join().unwrap();
+            }
+            text
+        };
+        let text = endless(Effect::Shift, Some(Duration::from_millis(50)));
+        assert!(text.matches(RESTORE_CURSOR).count() > 3, "{text:?}");
+        assert!(text.ends_with(&format!("{SHOW_CURSOR}{RESET}")));
+        // Once stopped, every line gets one settled frame.
+        let text = endless(Effect::FadeIn, None);
+        assert_eq!(text.matches(RESTORE_CURSOR).count(), 2, "{text:?}");
+        assert_eq!(
+            decode_cells(&text).concat(),
+            cell_colors(&mut Config::builder(), "ab\ncd\n")
+        );
+    }
+
+    #[test]
+    fn effects_settle_on_the_plain_rendering() {
+        let animated = |effect, mode, block, text: &str| {
+            let cfg = Config::builder()
+                .seed(1)
+                .animate(true)
+                .animate_block(block)
+                .effect(effect)
+                .duration(4)
+                .speed(1000.0)
+                .build()
+                .unwrap();
+            render(Printer::new(cfg, mode), text)
+        };
+        let effects = [
+            Effect::Wave,
+            Effect::Pulse,
+            Effect::Typewriter,
+            Effect::Marquee,
+            Effect::Sparkle,
+            Effect::FadeIn,
+        ];
+        for mode in [ColorMode::TrueColor, ColorMode::Ansi256] {
+            let mut printer = Printer::new(Config::builder().seed(1).build().unwrap(), mode);
+            let mut plain = Vec::new();
+            printer.print_text("abcd\n", &mut plain).unwrap();
+            let plain = String::from_utf8(plain).unwrap();
+            for effect in effects {
+                let text = animated(effect, mode, false, "abcd\n");
+                let frames: Vec<_> = text.split(RESTORE_CURSOR).skip(1).collect();
+                assert_eq!(frames.len(), 4);
+                assert!(frames[3].starts_with(&plain), "{effect:?} {text:?}");
+            }
+        }
+
+        let frames = |effect, block, text| {
+            let text = animated(effect, ColorMode::TrueColor, block, text);
+            let separator = if block { "\r\x1b[" } else { RESTORE_CURSOR };
+            text.split(separator)
+                .map(|frame| decode_cells(frame).concat().len())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            frames(Effect::Typewriter, false, "abcd\n")[1..],
+            [1, 2, 3, 4]
+        );
+        assert_eq!(frames(Effect::Typewriter, true, "ab\ncd\n"), [1, 2, 3, 4]);
+        let text = animated(Effect::Marquee, ColorMode::TrueColor, false, "abcd\n");
+        let first: String = text
+            .split(RESTORE_CURSOR)
+            .nth(1)
+            .unwrap()
+            .chars()
+            .filter(|ch| ('a'..='d').contains(ch))
+            .collect();
+        assert_eq!(first, "bcda");
+
+        // Fading in starts dark; sparkles follow the seed.
+        let text = animated(Effect::FadeIn, ColorMode::TrueColor, false, "abcd\n");
+        let fade: Vec<_> = text.split(RESTORE_CURSOR).map(decode_cells).collect();
+        let brightness = |color: &str| {
+            color
+                .split(';')
+                .map(|c| c.parse::<u32>().unwrap())
+                .sum::<u32>()
+        };
+        assert!(brightness(&fade[1][0][0]) < brightness(&fade[4][0][0]));
+        let grid = "abcdefgh\n".repeat(8);
+        let sparkle = || animated(Effect::Sparkle, ColorMode::Ansi256, true, &grid);
+        assert_eq!(sparkle(), sparkle());
+    }
+
+    #[test]
+    fn uncolored_printer_passes_text_through() {
+        let mut printer = Printer::new(Config::default(), ColorMode::Ansi256).with_color(false);
+        let mut output = Vec::new();
+        printer
+            .print_bytes(b"plain \x1b[1mtext\n", &mut output)
+            .unwrap();
+        printer.finalize(&mut output).unwrap();
+        assert_eq!(output, b"plain \x1b[1mtext\n");
+    }
+}
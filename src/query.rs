@@ -0,0 +1,271 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::Background;
+
+// XTGETTCAP for `Tc` and `RGB` (hex encoded), OSC 11 for the background, then
+// DA1. Every terminal answers DA1, so its reply marks the end of the others.
+const QUERIES: &[u8] = b"\x1bP+q5463;525742\x1b\\\x1b]11;?\x1b\\\x1b[c";
+const REPLY_CAP: usize = 4096;
+
+/// What the terminal said about itself; `None` where it did not answer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TerminalReport {
+    /// Whether XTGETTCAP reported `RGB` or `Tc`.
+    pub truecolor: Option<bool>,
+    /// The OSC 11 background color.
+    pub background: Option<(u8, u8, u8)>,
+}
+
+impl TerminalReport {
+    /// Light or dark, judged by the background's relative luminance.
+    pub fn background_kind(&self) -> Option<Background> {
+        let (r, g, b) = self.background?;
+        let luma = 0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64;
+        Some(if luma > 127.5 {
+            Background::Light
+        } else {
+            Background::Dark
+        })
+    }
+}
+
+/// Asks the controlling terminal for its capabilities and background color.
+///
+/// The terminal is switched to raw mode with `stty` for the exchange and
+/// restored afterwards. Returns `None` when there is no terminal or `stty`
+/// is unavailable; a terminal that stays silent yields an empty report once
+/// `timeout` has passed.
+pub fn query_terminal(timeout: Duration) -> Option<TerminalReport> {
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let saved = stty(&tty, &["-g"])?;
+    // `min 0 time 1` turns every read into a 100ms poll.
+    stty(&tty, &["raw", "-echo", "min", "0", "time", "1"])?;
+    let report = exchange(&mut tty, timeout);
+    let _ = stty(&tty, &[saved.trim()]);
+    report.ok()
+}
+
+/// Width of the terminal in columns: `$COLUMNS` when set, otherwise what
+/// `stty size` reports for the controlling terminal.
+pub fn terminal_width() -> Option<usize> {
+    terminal_size("COLUMNS", 1)
+}
+
+/// Height of the terminal in rows, from `$LINES` or `stty size`.
+pub fn terminal_height() -> Option<usize> {
+    terminal_size("LINES", 0)
+}
+
+/// `$var` when set, otherwise field `field` of `stty size` (rows, columns).
+fn terminal_size(var: &str, field: usize) -> Option<usize> {
+    if let Some(size) = std::env::var(var)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&size| size > 0)
+    {
+        return Some(size);
+    }
+    let tty = File::open("/dev/tty").ok()?;
+    let size = stty(&tty, &["size"])?;
+    size.split_whitespace()
+        .nth(field)?
+        .parse()
+        .ok()
+        .filter(|&size| size > 0)
+}
+
+fn stty(tty: &File, args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone().ok()?)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Sends the queries and collects replies until DA1 arrives or time runs out.
+pub(crate) fn exchange<T: Read + Write>(
+    tty: &mut T,
+    timeout: Duration,
+) -> io::Result<TerminalReport> {
+    tty.write_all(QUERIES)?;
+    tty.flush()?;
+    let deadline = Instant::now() + timeout;
+    let mut replies = Vec::new();
+    let mut buf = [0u8; 256];
+    while Instant::now() < deadline && replies.len() < REPLY_CAP {
+        match tty.read(&mut buf) {
+            Ok(0) => continue,
+            Ok(n) => replies.extend_from_slice(&buf[..n]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+        if parse_replies(&replies).1 {
+            break;
+        }
+    }
+    Ok(parse_replies(&replies).0)
+}
+
+/// Parses whatever replies arrived, and whether DA1 was among them.
+fn parse_replies(bytes: &[u8]) -> (TerminalReport, bool) {
+    let mut report = TerminalReport::default();
+    let mut saw_da1 = false;
+    let mut rest = bytes;
+    while let Some(start) = rest.iter().position(|&b| b == 0x1b) {
+        rest = &rest[start..];
+        match rest.get(1) {
+            Some(b'P') | Some(b']') => {
+                let Some((body, len)) = string_body(&rest[2..]) else {
+                    break;
+                };
+                if rest[1] == b'P' {
+                    parse_xtgettcap(body, &mut report);
+                } else if let Some(color) = body.strip_prefix(b"11;") {
+                    report.background = parse_osc_color(color).or(report.background);
+                }
+                rest = &rest[2 + len..];
+            }
+            Some(b'[') => {
+                let Some(end) = rest[2..].iter().position(|b| (0x40..=0x7e).contains(b)) else {
+                    break;
+                };
+                if rest.get(2) == Some(&b'?') && rest[2 + end] == b'c' {
+                    saw_da1 = true;
+                }
+                rest = &rest[3 + end..];
+            }
+            _ => rest = &rest[1..],
+        }
+    }
+    (report, saw_da1)
+}
+
+/// Body of a DCS or OSC string, and its length including the terminator.
+fn string_body(bytes: &[u8]) -> Option<(&[u8], usize)> {
+    for (idx, &byte) in bytes.iter().enumerate() {
+        match byte {
+            0x07 => return Some((&bytes[..idx], idx + 1)),
+            0x1b if bytes.get(idx + 1) == Some(&b'\\') => return Some((&bytes[..idx], idx + 2)),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `1+r<name>=<value>;...` on success, `0+r...` when a name is unknown.
+fn parse_xtgettcap(body: &[u8], report: &mut TerminalReport) {
+    if body.starts_with(b"1+r") {
+        let known = body[3..]
+            .split(|&b| b == b';')
+            .any(|item| item.starts_with(b"5463") || item.starts_with(b"525742"));
+        if known {
+            report.truecolor = Some(true);
+        }
+    } else if body.starts_with(b"0+r") && report.truecolor.is_none() {
+        report.truecolor = Some(false);
+    }
+}
+
+/// `rgb:RRRR/GGGG/BBBB` with one to four hex digits per channel.
+fn parse_osc_color(value: &[u8]) -> Option<(u8, u8, u8)> {
+    let value = std::str::from_utf8(value).ok()?;
+    let mut channels = value.strip_prefix("rgb:")?.split('/').map(|part| {
+        let max = 16u32.checked_pow(part.len() as u32)?.checked_sub(1)?;
+        let raw = u32::from_str_radix(part, 16).ok()?;
+        (1..=4)
+            .contains(&part.len())
+            .then(|| ((raw * 255 + max / 2) / max) as u8)
+    });
+    let rgb = (channels.next()??, channels.next()??, channels.next()??);
+    channels.next().is_none().then_some(rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for a pty: answers once it has seen the queries.
+    struct FakeTty {
+        written: Vec<u8>,
+        reply: Vec<u8>,
+        chunk: usize,
+    }
+
+    impl Read for FakeTty {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !self.written.ends_with(b"\x1b[c") {
+                return Ok(0);
+            }
+            let len = self.reply.len().min(self.chunk).min(buf.len());
+            buf[..len].copy_from_slice(&self.reply[..len]);
+            self.reply.drain(..len);
+            Ok(len)
+        }
+    }
+
+    impl Write for FakeTty {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run(reply: &[u8], chunk: usize) -> TerminalReport {
+        let mut tty = FakeTty {
+            written: Vec::new(),
+            reply: reply.to_vec(),
+            chunk,
+        };
+        exchange(&mut tty, Duration::from_millis(50)).unwrap()
+    }
+
+    #[test]
+    fn reads_truecolor_and_light_background() {
+        let reply = b"\x1bP1+r525742=382f382f38\x1b\\\x1b]11;rgb:ffff/ffff/dddd\x1b\\\x1b[?62;22c";
+        for chunk in [1, 5, reply.len()] {
+            let report = run(reply, chunk);
+            assert_eq!(report.truecolor, Some(true));
+            assert_eq!(report.background, Some((255, 255, 221)));
+            assert_eq!(report.background_kind(), Some(Background::Light));
+        }
+    }
+
+    #[test]
+    fn unknown_capability_and_bel_terminated_osc() {
+        let report = run(b"\x1bP0+r5463\x1b\\\x1b]11;rgb:1/2/3\x07\x1b[?1;2c", 3);
+        assert_eq!(report.truecolor, Some(false));
+        assert_eq!(report.background, Some((17, 34, 51)));
+        assert_eq!(report.background_kind(), Some(Background::Dark));
+    }
+
+    #[test]
+    fn silent_terminal_times_out_empty() {
+        let report = run(b"", 1);
+        assert_eq!(report, TerminalReport::default());
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        assert_eq!(parse_osc_color(b"rgb:ff/ff"), None);
+        assert_eq!(parse_osc_color(b"rgb:fffff/0/0"), None);
+        assert_eq!(parse_osc_color(b"#ffffff"), None);
+    }
+}
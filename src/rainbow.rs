@@ -0,0 +1,135 @@
+use std::{
+    f64::consts::PI,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SHIFT_COS: f64 = -0.5;
+const SHIFT_SIN: f64 = 0.866_025_403_784_438_6;
+
+/// Terminal background the colors are tuned for.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Background {
+    #[default]
+    Dark,
+    /// Keeps the sweep darker so yellows and cyans stay readable.
+    Light,
+}
+
+impl FromStr for Background {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "dark" => Ok(Background::Dark),
+            "light" => Ok(Background::Light),
+            _ => Err(format!(
+                "invalid value for --background: '{value}' (expected dark or light)"
+            )),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct RainbowState {
+    sin: f64,
+    cos: f64,
+    angle: f64,
+}
+
+impl RainbowState {
+    pub(crate) fn from_angle(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self { sin, cos, angle }
+    }
+
+    pub(crate) fn reset(&mut self, angle: f64) {
+        let (sin, cos) = angle.sin_cos();
+        self.sin = sin;
+        self.cos = cos;
+        self.angle = angle;
+    }
+
+    pub(crate) fn advance(&mut self, rot: RainbowRot) {
+        let sin = self.sin * rot.cos + self.cos * rot.sin;
+        let cos = self.cos * rot.cos - self.sin * rot.sin;
+        self.sin = sin;
+        self.cos = cos;
+        self.angle += rot.delta;
+    }
+
+    /// The phase half a turn away: the complementary hue.
+    pub(crate) fn opposite(self) -> Self {
+        Self {
+            sin: -self.sin,
+            cos: -self.cos,
+            angle: self.angle + PI,
+        }
+    }
+
+    /// Unwrapped phase angle, in radians.
+    pub(crate) fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    pub(crate) fn channels(&self, background: Background) -> (u8, u8, u8) {
+        (
+            encode_component(self.sin, background),
+            encode_component(self.sin * SHIFT_COS + self.cos * SHIFT_SIN, background),
+            encode_component(self.sin * SHIFT_COS - self.cos * SHIFT_SIN, background),
+        )
+    }
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct RainbowRot {
+    cos: f64,
+    sin: f64,
+    delta: f64,
+}
+
+impl RainbowRot {
+    pub(crate) fn new(delta: f64) -> Self {
+        let (sin, cos) = delta.sin_cos();
+        Self { cos, sin, delta }
+    }
+}
+
+/// Maps a sine in `-1..=1` to a channel: 128±127 on dark backgrounds, and
+/// 96±96 on light ones, where full-intensity yellow would vanish.
+fn encode_component(value: f64, background: Background) -> u8 {
+    let (center, amplitude) = match background {
+        Background::Dark => (128.0, 127.0),
+        Background::Light => (96.0, 96.0),
+    };
+    value.mul_add(amplitude, center).round().clamp(0.0, 255.0) as u8
+}
+
+pub(crate) fn initial_offset(seed: u64) -> f64 {
+    if seed == 0 {
+        random_seed_offset(256.0)
+    } else {
+        (seed % 256) as f64
+    }
+}
+
+/// Clock-derived rainbow offset in `0..range`, used when no seed is given.
+pub fn random_seed_offset(range: f64) -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| (dur.as_nanos() % (range as u128)) as f64)
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_background_darkens_the_sweep() {
+        assert_eq!(encode_component(1.0, Background::Dark), 255);
+        assert_eq!(encode_component(-1.0, Background::Dark), 1);
+        assert_eq!(encode_component(1.0, Background::Light), 192);
+        assert_eq!(encode_component(-1.0, Background::Light), 0);
+    }
+}
@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+/// What to do with colors the input already carries.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ExistingColor {
+    /// Pass input SGR through and color every glyph anyway.
+    #[default]
+    Override,
+    /// Leave glyphs uncolored while the input has set its own color.
+    Preserve,
+    /// Drop SGR sequences from the input.
+    Strip,
+}
+
+impl FromStr for ExistingColor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "override" => Ok(ExistingColor::Override),
+            "preserve" => Ok(ExistingColor::Preserve),
+            "strip" => Ok(ExistingColor::Strip),
+            _ => Err(format!(
+                "invalid value for --existing-color: '{value}' (expected override, preserve or strip)"
+            )),
+        }
+    }
+}
+
+/// Which colors the input has set explicitly.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct InputColors {
+    pub(crate) fg: bool,
+    pub(crate) bg: bool,
+}
+
+impl InputColors {
+    /// Applies the parameters of one `CSI ... m` sequence.
+    pub(crate) fn apply(&mut self, params: &[u8]) {
+        let mut params = params.split(|&b| b == b';');
+        while let Some(param) = params.next() {
+            // Colon sub-parameters (`38:2::r:g:b`) travel inside one parameter.
+            let code = param.split(|&b| b == b':').next().unwrap_or_default();
+            match code {
+                b"" | b"0" => *self = InputColors::default(),
+                b"39" => self.fg = false,
+                b"49" => self.bg = false,
+                b"38" | b"48" => {
+                    if code == b"38" {
+                        self.fg = true;
+                    } else {
+                        self.bg = true;
+                    }
+                    if param.len() == code.len() {
+                        // `38;5;n` and `38;2;r;g;b` spread over the next parameters.
+                        let extra = match params.next() {
+                            Some(b"5") => 1,
+                            Some(b"2") => 3,
+                            _ => 0,
+                        };
+                        params.by_ref().take(extra).for_each(drop);
+                    }
+                }
+                _ => match parse_code(code) {
+                    Some(30..=37 | 90..=97) => self.fg = true,
+                    Some(40..=47 | 100..=107) => self.bg = true,
+                    _ => {}
+                },
+            }
+        }
+    }
+}
+
+fn parse_code(code: &[u8]) -> Option<u16> {
+    std::str::from_utf8(code).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(fg, bg)` after applying each parameter list in turn.
+    fn colors(sequences: &[&str]) -> (bool, bool) {
+        let mut colors = InputColors::default();
+        for params in sequences {
+            colors.apply(params.as_bytes());
+        }
+        (colors.fg, colors.bg)
+    }
+
+    #[test]
+    fn tracks_foreground_and_background() {
+        assert_eq!(colors(&["01;34"]), (true, false));
+        assert_eq!(colors(&["31", "0"]), (false, false));
+        assert_eq!(colors(&["31", ""]), (false, false));
+        assert_eq!(colors(&["31", "39"]), (false, false));
+        assert_eq!(colors(&["42", "1"]), (false, true));
+        assert_eq!(colors(&["48:2::1:2:3"]), (false, true));
+    }
+
+    #[test]
+    fn extended_colors_skip_their_arguments() {
+        // The 0 here is a palette index, not a reset.
+        assert_eq!(colors(&["31", "48;5;0"]), (true, true));
+        assert_eq!(colors(&["38;2;0;0;0;1"]), (true, false));
+        assert_eq!(colors(&["38;2;0;0;0;0"]), (false, false));
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{self, BufReader, Read, Write},
+};
+
+use crate::Printer;
+
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Failure while colorizing a stream; a closed downstream pipe is kept apart
+/// so callers can exit quietly.
+#[derive(Debug)]
+pub enum StreamError {
+    BrokenPipe,
+    Io(io::Error),
+}
+
+impl From<io::Error> for StreamError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::BrokenPipe {
+            StreamError::BrokenPipe
+        } else {
+            StreamError::Io(err)
+        }
+    }
+}
+
+impl From<StreamError> for io::Error {
+    fn from(err: StreamError) -> Self {
+        match err {
+            StreamError::BrokenPipe => io::Error::from(io::ErrorKind::BrokenPipe),
+            StreamError::Io(err) => err,
+        }
+    }
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::BrokenPipe => f.write_str("broken pipe"),
+            StreamError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// Copies `reader` into `writer` through `printer`, keeping partial UTF-8
+/// sequences and escape state intact across reads.
+pub fn process_stream<R: Read>(
+    reader: R,
+    writer: &mut dyn Write,
+    printer: &mut Printer,
+) -> Result<(), StreamError> {
+    if !printer.use_color {
+        let mut reader = BufReader::new(reader);
+        io::copy(&mut reader, writer).map_err(StreamError::from)?;
+        return Ok(());
+    }
+
+    if printer.cfg.animate {
+        process_stream_buffered(reader, writer, printer)
+    } else {
+        process_stream_streaming(reader, writer, printer)
+    }
+}
+
+fn process_stream_buffered<R: Read>(
+    reader: R,
+    writer: &mut dyn Write,
+    printer: &mut Printer,
+) -> Result<(), StreamError> {
+    let mut reader = BufReader::new(reader);
+    let mut chunk = [0u8; READ_CHUNK];
+    let mut line_buf = Vec::new();
+    loop {
+        let read = reader.read(&mut chunk).map_err(StreamError::from)?;
+        if read == 0 {
+            if !line_buf.is_empty() {
+                flush_line(&mut line_buf, false, printer, writer)?;
+            }
+            break;
+        }
+
+        let mut start = 0;
+        for (idx, &byte) in chunk[..read].iter().enumerate() {
+            if byte == b'\n' {
+                line_buf.extend_from_slice(&chunk[start..idx]);
+                flush_line(&mut line_buf, true, printer, writer)?;
+                start = idx + 1;
+            }
+        }
+
+        if start < read {
+            line_buf.extend_from_slice(&chunk[start..read]);
+        }
+    }
+    printer.flush_pending(writer).map_err(StreamError::from)
+}
+
+fn process_stream_streaming<R: Read>(
+    mut reader: R,
+    writer: &mut dyn Write,
+    printer: &mut Printer,
+) -> Result<(), StreamError> {
+    let mut buffer = [0u8; READ_CHUNK + 4];
+    let mut carry = 0usize;
+
+    loop {
+        let read = reader
+            .read(&mut buffer[carry..])
+            .map_err(StreamError::from)?;
+        if read == 0 {
+            break;
+        }
+        let total = carry + read;
+        carry = consume_bytes(&buffer[..total], printer, writer).map_err(StreamError::from)?;
+        buffer.copy_within(total - carry..total, 0);
+    }
+
+    if carry > 0 {
+        printer
+            .write_replacement(writer)
+            .map_err(StreamError::from)?;
+    }
+
+    printer.flush_pending(writer).map_err(StreamError::from)
+}
+
+/// Colors the valid UTF-8 in `bytes` and returns the length of a trailing
+/// incomplete sequence that the caller must prepend to the next chunk.
+pub(crate) fn consume_bytes(
+    bytes: &[u8],
+    printer: &mut Printer,
+    writer: &mut dyn Write,
+) -> io::Result<usize> {
+    let mut offset = 0usize;
+    while offset < bytes.len() {
+        match std::str::from_utf8(&bytes[offset..]) {
+            Ok(valid) => {
+                consume_segment(valid, printer, writer)?;
+                offset = bytes.len();
+            }
+            Err(err) => {
+                let valid_up_to = err.valid_up_to();
+                if valid_up_to > 0 {
+                    let slice = std::str::from_utf8(&bytes[offset..offset + valid_up_to])
+                        .expect("validator provided a valid prefix");
+                    consume_segment(slice, printer, writer)?;
+                    offset += valid_up_to;
+                    continue;
+                }
+                if let Some(error_len) = err.error_len() {
+                    printer.write_replacement(writer)?;
+                    offset += error_len;
+                    continue;
+                }
+                return Ok(bytes.len() - offset);
+            }
+        }
+    }
+    Ok(0)
+}
+
+fn consume_segment(segment: &str, printer: &mut Printer, writer: &mut dyn Write) -> io::Result<()> {
+    if segment.is_empty() {
+        return Ok(());
+    }
+    let mut start = 0;
+    for (idx, ch) in segment.char_indices() {
+        if ch == '\n' {
+            if idx > start {
+                printer.write_plain_segment(&segment[start..idx], writer)?;
+            }
+            printer.finish_line(writer)?;
+            start = idx + ch.len_utf8();
+        }
+    }
+    if start < segment.len() {
+        printer.write_plain_segment(&segment[start..], writer)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn flush_line(
+    line_buf: &mut Vec<u8>,
+    had_newline: bool,
+    printer: &mut Printer,
+    writer: &mut dyn Write,
+) -> Result<(), StreamError> {
+    if line_buf.is_empty() && !had_newline {
+        return Ok(());
+    }
+    let text = match String::from_utf8_lossy(line_buf) {
+        Cow::Owned(s) => s,
+        Cow::Borrowed(s) => s.to_string(),
+    };
+    printer
+        .print_line(&text, had_newline, writer)
+        .map_err(StreamError::from)?;
+    line_buf.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorMode, Config};
+    use std::io::{self, Read};
+
+    #[test]
+    fn streaming_preserves_escape_sequences() {
+        let mut printer = Printer::new(Config::default(), ColorMode::Ansi256).with_offset(0.0);
+        let mut output = Vec::new();
+        let input = b"\x1b[31mhello\nworld";
+        let reader = Chunked::new(&input[..], 2);
+
+        process_stream_streaming(reader, &mut output, &mut printer).unwrap();
+
+        assert!(
+            output.windows(5).any(|w| w == b"\x1b[31m"),
+            "escape sequence missing in {:?}",
+            output
+        );
+    }
+
+    #[test]
+    fn streaming_replaces_invalid_utf8() {
+        let mut printer = Printer::new(Config::default(), ColorMode::Ansi256).with_offset(0.0);
+        let mut output = Vec::new();
+        let input = [0xFF, 0xFF, b'\n'];
+        let reader = Chunked::new(&input, 1);
+
+        process_stream_streaming(reader, &mut output, &mut printer).unwrap();
+
+        assert!(
+            output.windows(3).any(|w| w == [0xEF, 0xBF, 0xBD]),
+            "replacement char missing in {:?}",
+            output
+        );
+    }
+
+    struct Chunked<'a> {
+        data: &'a [u8],
+        pos: usize,
+        chunk: usize,
+    }
+
+    impl<'a> Chunked<'a> {
+        fn new(data: &'a [u8], chunk: usize) -> Self {
+            Self {
+                data,
+                pos: 0,
+                chunk,
+            }
+        }
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pos >= self.data.len() {
+                return Ok(0);
+            }
+            let remaining = self.data.len() - self.pos;
+            let take = remaining.min(self.chunk).min(buf.len());
+            buf[..take].copy_from_slice(&self.data[self.pos..self.pos + take]);
+            self.pos += take;
+            Ok(take)
+        }
+    }
+}
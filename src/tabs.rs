@@ -0,0 +1,91 @@
+use std::str::FromStr;
+
+/// Where a tab character moves the cursor to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TabStops {
+    /// A stop every N columns.
+    Every(usize),
+    /// Stops at the listed columns (strictly increasing, counted from 0);
+    /// past the last one they repeat at the last interval.
+    List(Vec<usize>),
+}
+
+impl Default for TabStops {
+    fn default() -> Self {
+        TabStops::Every(8)
+    }
+}
+
+impl FromStr for TabStops {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid value for --tabs: '{value}' \
+                 (expected a width or increasing comma-separated columns)"
+            )
+        };
+        let stops = value
+            .split(',')
+            .map(|stop| stop.trim().parse::<usize>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match stops.as_slice() {
+            [width] if *width > 0 => Ok(TabStops::Every(*width)),
+            [first, ..] if *first > 0 && stops.windows(2).all(|pair| pair[0] < pair[1]) => {
+                Ok(TabStops::List(stops))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TabStops {
+    /// The first stop after `column`.
+    pub fn next_stop(&self, column: usize) -> usize {
+        match self {
+            TabStops::Every(width) => (column / width + 1) * width,
+            TabStops::List(stops) => {
+                if let Some(&stop) = stops.iter().find(|&&stop| stop > column) {
+                    return stop;
+                }
+                let last = stops[stops.len() - 1];
+                let interval = match stops.len() {
+                    1 => last,
+                    len => last - stops[len - 2],
+                };
+                last + ((column - last) / interval + 1) * interval
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_widths_and_lists() {
+        assert_eq!("4".parse::<TabStops>(), Ok(TabStops::Every(4)));
+        assert_eq!(
+            "4, 10,20".parse::<TabStops>(),
+            Ok(TabStops::List(vec![4, 10, 20]))
+        );
+        for bad in ["0", "", "8,4", "4,4", "a,b", "0,8"] {
+            assert!(bad.parse::<TabStops>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn next_stop_follows_the_list() {
+        assert_eq!(TabStops::default().next_stop(0), 8);
+        assert_eq!(TabStops::default().next_stop(7), 8);
+        assert_eq!(TabStops::default().next_stop(8), 16);
+        let list = TabStops::List(vec![4, 10, 20]);
+        assert_eq!(list.next_stop(0), 4);
+        assert_eq!(list.next_stop(4), 10);
+        assert_eq!(list.next_stop(19), 20);
+        assert_eq!(list.next_stop(20), 30);
+        assert_eq!(list.next_stop(31), 40);
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+const MAGIC_LEGACY: u16 = 0o432;
+const MAGIC_32BIT: u16 = 0o1036;
+const COLORS_INDEX: usize = 13;
+
+/// The color-related capabilities of a compiled terminfo entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Terminfo {
+    /// The `colors` number, if present.
+    pub(crate) colors: Option<u32>,
+    /// The `RGB` or `Tc` extended boolean.
+    pub(crate) rgb: bool,
+}
+
+impl Terminfo {
+    /// Looks `term` up in the usual ncurses search path.
+    pub(crate) fn load(term: &str) -> Option<Self> {
+        let first = term.chars().next()?;
+        if term.contains('/') || term.contains("..") {
+            return None;
+        }
+        search_dirs().into_iter().find_map(|dir| {
+            let by_char = dir.join(first.to_string()).join(term);
+            let by_hex = dir.join(format!("{:x}", first as u32)).join(term);
+            read_entry(&by_char).or_else(|| read_entry(&by_hex))
+        })
+    }
+
+    /// Parses the legacy (16-bit) and ncurses 6.1 (32-bit) compiled formats,
+    /// including the extended capability section where `RGB` and `Tc` live.
+    pub(crate) fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data, pos: 0 };
+        let magic = reader.u16()?;
+        let number_width = match magic {
+            MAGIC_LEGACY => 2,
+            MAGIC_32BIT => 4,
+            _ => return None,
+        };
+        let names_size = reader.count()?;
+        let bool_count = reader.count()?;
+        let num_count = reader.count()?;
+        let str_count = reader.count()?;
+        let str_table_size = reader.count()?;
+
+        reader.skip(names_size + bool_count)?;
+        reader.align();
+        let mut info = Terminfo::default();
+        for idx in 0..num_count {
+            let value = reader.number(number_width)?;
+            if idx == COLORS_INDEX && value >= 0 {
+                info.colors = Some(value as u32);
+            }
+        }
+        reader.skip(str_count * 2 + str_table_size)?;
+        reader.align();
+
+        // The extended section is optional.
+        let Some(ext_bool_count) = reader.count() else {
+            return Some(info);
+        };
+        let ext_num_count = reader.count()?;
+        let ext_str_count = reader.count()?;
+        let _ext_item_count = reader.count()?;
+        let ext_table_size = reader.count()?;
+
+        let ext_bools = reader.take(ext_bool_count)?;
+        reader.align();
+        reader.skip(ext_num_count * number_width)?;
+        let mut value_offsets = Vec::with_capacity(ext_str_count);
+        for _ in 0..ext_str_count {
+            value_offsets.push(reader.u16()? as i16);
+        }
+        let name_count = ext_bool_count + ext_num_count + ext_str_count;
+        let mut name_offsets = Vec::with_capacity(name_count);
+        for _ in 0..name_count {
+            name_offsets.push(reader.u16()? as i16);
+        }
+        let table = reader.take(ext_table_size)?;
+
+        // Names follow the last string value in the table.
+        let names_base = value_offsets
+            .iter()
+            .filter(|&&offset| offset >= 0)
+            .filter_map(|&offset| {
+                let start = offset as usize;
+                let len = table.get(start..)?.iter().position(|&b| b == 0)?;
+                Some(start + len + 1)
+            })
+            .max()
+            .unwrap_or(0);
+        for (idx, &set) in ext_bools.iter().enumerate() {
+            let offset = name_offsets[idx];
+            if set != 1 || offset < 0 {
+                continue;
+            }
+            let name = c_string(table, names_base + offset as usize);
+            if name == Some(b"RGB".as_slice()) || name == Some(b"Tc".as_slice()) {
+                info.rgb = true;
+            }
+        }
+        Some(info)
+    }
+}
+
+fn read_entry(path: &Path) -> Option<Terminfo> {
+    Terminfo::parse(&fs::read(path).ok()?)
+}
+
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push(Path::new(&home).join(".terminfo"));
+    }
+    if let Ok(list) = env::var("TERMINFO_DIRS") {
+        dirs.extend(
+            list.split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        );
+    }
+    for dir in ["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo"] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+fn c_string(table: &[u8], start: usize) -> Option<&[u8]> {
+    let rest = table.get(start..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    Some(&rest[..len])
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn align(&mut self) {
+        if self.pos % 2 == 1 {
+            self.pos += 1;
+        }
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.take(2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Header counts; -1 means "absent" and reads as zero.
+    fn count(&mut self) -> Option<usize> {
+        Some((self.u16()? as i16).max(0) as usize)
+    }
+
+    fn number(&mut self, width: usize) -> Option<i32> {
+        let bytes = self.take(width)?;
+        Some(match width {
+            2 => i16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+            _ => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compiles a minimal entry: `colors` plus optional extended booleans.
+    fn compile(magic: u16, colors: i32, ext_bools: &[&str]) -> Vec<u8> {
+        let width = if magic == MAGIC_32BIT { 4 } else { 2 };
+        let names = b"test|synthetic entry\0";
+        let mut out = Vec::new();
+        let push16 = |out: &mut Vec<u8>, value: i16| out.extend_from_slice(&value.to_le_bytes());
+        push16(&mut out, magic as i16);
+        push16(&mut out, names.len() as i16);
+        push16(&mut out, 1);
+        push16(&mut out, COLORS_INDEX as i16 + 1);
+        push16(&mut out, 0);
+        push16(&mut out, 0);
+        out.extend_from_slice(names);
+        out.push(1);
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+        for idx in 0..=COLORS_INDEX {
+            let value = if idx == COLORS_INDEX { colors } else { -1 };
+            out.extend_from_slice(&value.to_le_bytes()[..width]);
+        }
+        if ext_bools.is_empty() {
+            return out;
+        }
+        let mut table = Vec::new();
+        let mut offsets = Vec::new();
+        for name in ext_bools {
+            offsets.push(table.len() as i16);
+            table.extend_from_slice(name.as_bytes());
+            table.push(0);
+        }
+        push16(&mut out, ext_bools.len() as i16);
+        push16(&mut out, 0);
+        push16(&mut out, 0);
+        push16(&mut out, ext_bools.len() as i16);
+        push16(&mut out, table.len() as i16);
+        out.extend(std::iter::repeat_n(1u8, ext_bools.len()));
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+        for offset in offsets {
+            push16(&mut out, offset);
+        }
+        out.extend_from_slice(&table);
+        out
+    }
+
+    #[test]
+    fn parses_legacy_colors() {
+        let info = Terminfo::parse(&compile(MAGIC_LEGACY, 8, &[])).unwrap();
+        assert_eq!(info.colors, Some(8));
+        assert!(!info.rgb);
+    }
+
+    #[test]
+    fn parses_32bit_numbers_and_extended_rgb() {
+        let info = Terminfo::parse(&compile(MAGIC_32BIT, 0x100_0000, &["AX", "RGB"])).unwrap();
+        assert_eq!(info.colors, Some(0x100_0000));
+        assert!(info.rgb);
+        let info = Terminfo::parse(&compile(MAGIC_LEGACY, 256, &["Tc"])).unwrap();
+        assert!(info.rgb);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(Terminfo::parse(b""), None);
+        assert_eq!(Terminfo::parse(b"\x1a\x01\xff"), None);
+        assert_eq!(Terminfo::parse(&[0u8; 64]), None);
+        let mut truncated = compile(MAGIC_32BIT, 256, &["RGB"]);
+        truncated.truncate(truncated.len() - 3);
+        assert_eq!(Terminfo::parse(&truncated), None);
+    }
+}
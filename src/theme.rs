@@ -0,0 +1,174 @@
+use crate::Gradient;
+
+/// A named gradient with a hand-tuned 256-color fallback ramp.
+#[derive(Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    pub description: &'static str,
+    stops: &'static [(u8, u8, u8)],
+    ansi256: &'static [u8],
+}
+
+impl Theme {
+    pub fn gradient(&self) -> Gradient {
+        Gradient::new(self.stops.to_vec())
+            .expect("built-in themes have stops")
+            .with_ansi256(self.ansi256.to_vec())
+    }
+}
+
+pub fn find_theme(name: &str) -> Option<&'static Theme> {
+    THEMES
+        .iter()
+        .find(|theme| theme.name.eq_ignore_ascii_case(name))
+}
+
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "pride",
+        description: "six-stripe rainbow flag",
+        stops: &[
+            (228, 3, 3),
+            (255, 140, 0),
+            (255, 237, 0),
+            (0, 128, 38),
+            (0, 77, 255),
+            (117, 7, 135),
+        ],
+        ansi256: &[
+            160, 202, 208, 214, 220, 226, 148, 70, 28, 29, 25, 27, 63, 56, 90,
+        ],
+    },
+    Theme {
+        name: "trans",
+        description: "transgender flag",
+        stops: &[
+            (91, 206, 250),
+            (245, 169, 184),
+            (255, 255, 255),
+            (245, 169, 184),
+            (91, 206, 250),
+        ],
+        ansi256: &[81, 117, 153, 218, 224, 231, 224, 218, 153, 117, 81],
+    },
+    Theme {
+        name: "bi",
+        description: "bisexual flag",
+        stops: &[(214, 2, 112), (155, 79, 150), (0, 56, 168)],
+        ansi256: &[162, 162, 126, 96, 97, 61, 25, 25],
+    },
+    Theme {
+        name: "pan",
+        description: "pansexual flag",
+        stops: &[(255, 33, 140), (255, 216, 0), (33, 177, 255)],
+        ansi256: &[198, 204, 209, 214, 220, 184, 114, 45, 39],
+    },
+    Theme {
+        name: "lesbian",
+        description: "five-stripe lesbian flag",
+        stops: &[
+            (213, 45, 0),
+            (255, 154, 86),
+            (255, 255, 255),
+            (211, 98, 164),
+            (163, 2, 98),
+        ],
+        ansi256: &[166, 202, 209, 216, 224, 231, 218, 175, 169, 162, 125],
+    },
+    Theme {
+        name: "nonbinary",
+        description: "non-binary flag",
+        stops: &[
+            (252, 244, 52),
+            (255, 255, 255),
+            (156, 89, 209),
+            (44, 44, 44),
+        ],
+        ansi256: &[227, 229, 231, 189, 141, 134, 97, 240, 236],
+    },
+    Theme {
+        name: "ace",
+        description: "asexual flag",
+        stops: &[(0, 0, 0), (163, 163, 163), (255, 255, 255), (128, 0, 128)],
+        ansi256: &[236, 239, 243, 247, 251, 255, 182, 133, 90],
+    },
+    Theme {
+        name: "pastel",
+        description: "soft candy colors",
+        stops: &[
+            (255, 179, 186),
+            (255, 223, 186),
+            (255, 255, 186),
+            (186, 255, 201),
+            (186, 225, 255),
+            (220, 198, 255),
+        ],
+        ansi256: &[217, 223, 229, 193, 158, 153, 189],
+    },
+    Theme {
+        name: "fire",
+        description: "embers to white-hot",
+        stops: &[
+            (128, 0, 0),
+            (220, 20, 0),
+            (255, 110, 0),
+            (255, 190, 0),
+            (255, 240, 120),
+        ],
+        ansi256: &[88, 124, 160, 196, 202, 208, 214, 220, 221, 228],
+    },
+    Theme {
+        name: "ocean",
+        description: "deep sea to surf",
+        stops: &[
+            (0, 20, 60),
+            (0, 70, 140),
+            (0, 140, 190),
+            (60, 200, 220),
+            (170, 240, 240),
+        ],
+        ansi256: &[17, 18, 24, 25, 31, 37, 38, 44, 80, 116, 159],
+    },
+    Theme {
+        name: "neon",
+        description: "saturated signage glow",
+        stops: &[
+            (255, 0, 255),
+            (0, 255, 255),
+            (57, 255, 20),
+            (255, 255, 0),
+            (255, 20, 147),
+        ],
+        ansi256: &[201, 165, 51, 50, 46, 118, 226, 214, 198],
+    },
+    Theme {
+        name: "mono",
+        description: "grayscale ramp",
+        stops: &[(48, 48, 48), (255, 255, 255)],
+        ansi256: &[236, 238, 240, 242, 244, 246, 248, 250, 252, 254, 231],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GradientMode;
+
+    #[test]
+    fn themes_are_found_case_insensitively() {
+        assert_eq!(find_theme("Ocean").map(|t| t.name), Some("ocean"));
+        assert!(find_theme("plaid").is_none());
+    }
+
+    #[test]
+    fn every_theme_has_a_ramp() {
+        for theme in THEMES {
+            let gradient = theme.gradient();
+            assert!(
+                gradient.sample_ansi256(0.3, GradientMode::Cycle).is_some(),
+                "{} lacks a 256-color ramp",
+                theme.name
+            );
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Terminal cell widths (UAX #11).
+
+use crate::{
+    TabStops,
+    escape::{EscapeParser, Step},
+    grapheme::Segmenter,
+};
+
+mod table;
+
+const VARIATION_SELECTOR_16: char = '\u{FE0F}';
+
+/// Cells taken by `ch` on its own: 0 for controls and marks, 2 for East
+/// Asian wide and fullwidth characters, 1 otherwise.
+pub(crate) fn char_width(ch: char) -> usize {
+    let cp = ch as u32;
+    if cp < 0x80 {
+        return usize::from(!ch.is_ascii_control());
+    }
+    table::WIDTHS
+        .binary_search_by(|&(first, last, _)| {
+            if last < cp {
+                std::cmp::Ordering::Less
+            } else if first > cp {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .map_or(1, |idx| table::WIDTHS[idx].2 as usize)
+}
+
+/// Running width of one grapheme cluster: the sum of its characters,
+/// capped at two cells, and two whenever VS16 asks for emoji presentation.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ClusterWidth(usize);
+
+impl ClusterWidth {
+    pub(crate) fn push(&mut self, ch: char) {
+        self.0 = if ch == VARIATION_SELECTOR_16 {
+            2
+        } else {
+            (self.0 + char_width(ch)).min(2)
+        };
+    }
+
+    pub(crate) fn cells(self) -> usize {
+        self.0
+    }
+}
+
+/// Cells `text` covers as output: escape sequences take none and tabs
+/// reach the next stop.
+pub(crate) fn text_width(text: &str, tabs: &TabStops) -> usize {
+    let mut escape = EscapeParser::new();
+    let mut segmenter = Segmenter::new();
+    let mut column = 0;
+    let mut cluster = ClusterWidth::default();
+    for ch in text.chars() {
+        let text = matches!(escape.advance(ch), Step::Text | Step::Abandon);
+        if !text || ch.is_control() {
+            column += cluster.cells();
+            cluster = ClusterWidth::default();
+            segmenter.reset();
+            if text && ch == '\t' {
+                column = tabs.next_stop(column);
+            }
+            continue;
+        }
+        if segmenter.starts_cluster(ch) {
+            column += cluster.cells();
+            cluster = ClusterWidth::default();
+        }
+        cluster.push(ch);
+    }
+    column + cluster.cells()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_and_zero_width_characters() {
+        assert_eq!(char_width('a'), 1);
+        assert_eq!(char_width('\u{7}'), 0);
+        assert_eq!(char_width('漢'), 2);
+        assert_eq!(char_width('\u{FF21}'), 2);
+        assert_eq!(char_width('\u{301}'), 0);
+        assert_eq!(char_width('\u{200D}'), 0);
+        assert_eq!(char_width('\u{1F600}'), 2);
+        assert_eq!(char_width('\u{E0001}'), 0);
+    }
+
+    #[test]
+    fn clusters_take_at_most_two_cells() {
+        let display_width = |text| text_width(text, &TabStops::default());
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(
+            display_width("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"),
+            2
+        );
+        assert_eq!(display_width("\u{1F1E9}\u{1F1EA}"), 2);
+        assert_eq!(display_width("\u{2764}\u{FE0F}"), 2);
+        assert_eq!(display_width("\u{1100}\u{1161}\u{11A8}"), 2);
+    }
+
+    #[test]
+    fn text_width_skips_escapes_and_expands_tabs() {
+        let tabs = TabStops::default();
+        assert_eq!(text_width("\x1b[1m\u{65E5}\x1b[0m", &tabs), 2);
+        assert_eq!(text_width("ab\tc", &tabs), 9);
+        assert_eq!(
+            text_width("\x1b]8;;http://x\x07link\x1b]8;;\x07\n", &tabs),
+            4
+        );
+    }
+}
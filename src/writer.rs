@@ -0,0 +1,284 @@
+use std::io::{self, Write};
+
+use crate::{
+    Printer,
+    stream::{consume_bytes, flush_line},
+};
+
+/// [`Write`] adapter that colors everything written through it.
+///
+/// UTF-8 sequences and escape sequences may be split across `write` calls.
+/// `flush` writes out what is pending and ends the current color run, leaving
+/// hyperlinks and attributes open; dropping the writer (or calling
+/// [`finish`](RainbowWriter::finish)) emits whatever is still pending and
+/// resets the terminal.
+pub struct RainbowWriter<W: Write> {
+    inner: Option<W>,
+    printer: Printer,
+    carry: [u8; 4],
+    carry_len: usize,
+    line_buf: Vec<u8>,
+}
+
+impl<W: Write> RainbowWriter<W> {
+    pub fn new(inner: W, printer: Printer) -> Self {
+        Self {
+            inner: Some(inner),
+            printer,
+            carry: [0u8; 4],
+            carry_len: 0,
+            line_buf: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("writer already finished")
+    }
+
+    pub fn printer(&self) -> &Printer {
+        &self.printer
+    }
+
+    /// Emits any pending output, resets the terminal and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.finish_pending()?;
+        Ok(self.inner.take().expect("writer already finished"))
+    }
+
+    fn finish_pending(&mut self) -> io::Result<()> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(());
+        };
+        if !self.line_buf.is_empty() {
+            flush_line(&mut self.line_buf, false, &mut self.printer, inner)?;
+        }
+        if self.carry_len > 0 {
+            self.carry_len = 0;
+            self.printer.write_replacement(inner)?;
+        }
+        self.printer.finalize(inner)
+    }
+
+    fn write_colored(&mut self, buf: &[u8]) -> io::Result<()> {
+        let inner = self.inner.as_mut().expect("writer already finished");
+        let mut input = buf;
+        while self.carry_len > 0 && !input.is_empty() {
+            self.carry[self.carry_len] = input[0];
+            self.carry_len += 1;
+            input = &input[1..];
+            let pending = consume_bytes(&self.carry[..self.carry_len], &mut self.printer, inner)?;
+            let start = self.carry_len - pending;
+            self.carry.copy_within(start..self.carry_len, 0);
+            self.carry_len = pending;
+        }
+        if input.is_empty() {
+            return Ok(());
+        }
+        let pending = consume_bytes(input, &mut self.printer, inner)?;
+        self.carry[..pending].copy_from_slice(&input[input.len() - pending..]);
+        self.carry_len = pending;
+        Ok(())
+    }
+
+    fn write_animated(&mut self, buf: &[u8]) -> io::Result<()> {
+        let inner = self.inner.as_mut().expect("writer already finished");
+        let mut start = 0;
+        for (idx, &byte) in buf.iter().enumerate() {
+            if byte == b'\n' {
+                self.line_buf.extend_from_slice(&buf[start..idx]);
+                flush_line(&mut self.line_buf, true, &mut self.printer, inner)?;
+                start = idx + 1;
+            }
+        }
+        self.line_buf.extend_from_slice(&buf[start..]);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for RainbowWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.printer.use_color {
+            return self
+                .inner
+                .as_mut()
+                .expect("writer already finished")
+                .write(buf);
+        }
+        if self.printer.cfg.animate {
+            self.write_animated(buf)?;
+        } else {
+            self.write_colored(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let inner = self.inner.as_mut().expect("writer already finished");
+        self.printer.flush_pending(inner)?;
+        inner.flush()
+    }
+}
+
+impl<W: Write> Drop for RainbowWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish_pending();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorMode, Config};
+
+    fn writer() -> RainbowWriter<Vec<u8>> {
+        let cfg = Config::builder().seed(3).build().unwrap();
+        RainbowWriter::new(Vec::new(), Printer::new(cfg, ColorMode::TrueColor))
+    }
+
+    fn visible(output: &[u8]) -> String {
+        let text = String::from_utf8(output.to_vec()).unwrap();
+        let mut cleaned = String::new();
+        let mut in_escape = false;
+        for ch in text.chars() {
+            if in_escape {
+                in_escape = !ch.is_ascii_alphabetic();
+            } else if ch == '\x1b' {
+                in_escape = true;
+            } else {
+                cleaned.push(ch);
+            }
+        }
+        cleaned
+    }
+
+    #[test]
+    fn garbage_renders_the_same_however_it_is_split() {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize
+        };
+        let alphabet: &[&[u8]] = &[
+            b"\x1b",
+            b"[",
+            b"]",
+            b"P",
+            b";",
+            b"1",
+            b"m",
+            b"\x07",
+            b"\\",
+            b"\x18",
+            b"\n",
+            b"\r",
+            b"\t",
+            b"x",
+            "\u{9b}".as_bytes(),
+            "\u{9c}".as_bytes(),
+            "\u{301}".as_bytes(),
+            "\u{65E5}".as_bytes(),
+            b"\xff",
+            b"\xe6",
+        ];
+        for _ in 0..50 {
+            let input: Vec<u8> = (0..400)
+                .flat_map(|_| alphabet[next() % alphabet.len()].iter().copied())
+                .collect();
+            let mut whole = writer();
+            whole.write_all(&input).unwrap();
+            let whole = whole.finish().unwrap();
+
+            let mut split = writer();
+            let mut rest = &input[..];
+            while !rest.is_empty() {
+                let (chunk, tail) = rest.split_at((next() % 7 + 1).min(rest.len()));
+                split.write_all(chunk).unwrap();
+                rest = tail;
+            }
+            assert_eq!(split.finish().unwrap(), whole);
+            assert!(whole.ends_with(b"\x1b[0m"));
+        }
+    }
+
+    #[test]
+    fn clusters_continue_across_writes() {
+        let mut writer = writer();
+        writer.write_all(b"e").unwrap();
+        writer.write_all("\u{301}x".as_bytes()).unwrap();
+        let output = writer.finish().unwrap();
+        assert_eq!(visible(&output), "e\u{301}x");
+        assert_eq!(
+            String::from_utf8(output).unwrap().matches("38;2;").count(),
+            2
+        );
+    }
+
+    #[test]
+    fn split_utf8_sequences_are_reassembled() {
+        let mut rainbow = writer();
+        let bytes = "héllo ✓\n".as_bytes();
+        for byte in bytes {
+            rainbow.write_all(std::slice::from_ref(byte)).unwrap();
+        }
+        let output = rainbow.finish().unwrap();
+        assert_eq!(visible(&output), "héllo ✓\n");
+        assert!(!output.windows(3).any(|w| w == "\u{FFFD}".as_bytes()));
+    }
+
+    #[test]
+    fn escape_state_survives_between_writes() {
+        let mut rainbow = writer();
+        rainbow.write_all(b"\x1b[1").unwrap();
+        rainbow.write_all(b";31mhi").unwrap();
+        let output = rainbow.finish().unwrap();
+        assert!(output.windows(7).any(|w| w == b"\x1b[1;31m"));
+        assert_eq!(visible(&output), "hi");
+    }
+
+    #[test]
+    fn writeln_and_drop_reset_terminal() {
+        let mut output = Vec::new();
+        {
+            let cfg = Config::builder().seed(3).build().unwrap();
+            let printer = Printer::new(cfg, ColorMode::Ansi256);
+            let mut rainbow = RainbowWriter::new(&mut output, printer);
+            writeln!(rainbow, "value = {}", 42).unwrap();
+        }
+        assert_eq!(visible(&output), "value = 42\n");
+        assert!(output.ends_with(b"\x1b[0m"));
+    }
+
+    #[test]
+    fn flush_keeps_links_and_attributes_open() {
+        let count =
+            |output: &[u8], needle: &str| String::from_utf8_lossy(output).matches(needle).count();
+        let head = b"\x1b[1mbold \x1b]8;;https://example.com\x1b\\li";
+        let tail = b"nk\x1b]8;;\x1b\\\n";
+        let mut whole = writer();
+        whole.write_all(head).unwrap();
+        whole.write_all(tail).unwrap();
+        let whole = whole.finish().unwrap();
+
+        let mut flushed = writer();
+        flushed.write_all(head).unwrap();
+        flushed.flush().unwrap();
+        let at_flush = flushed.get_ref().clone();
+        assert_eq!(count(&at_flush, "\x1b[0m"), 0, "{at_flush:?}");
+        assert_eq!(count(&at_flush, "\x1b]8;;"), 1, "{at_flush:?}");
+        flushed.write_all(tail).unwrap();
+        let flushed = flushed.finish().unwrap();
+        assert_eq!(visible(&flushed), visible(&whole));
+        assert_eq!(count(&flushed, "\x1b[0m"), count(&whole, "\x1b[0m"));
+        assert_eq!(count(&flushed, "\x1b]8;;"), count(&whole, "\x1b]8;;"));
+    }
+
+    #[test]
+    fn truncated_sequence_becomes_replacement_on_finish() {
+        let mut rainbow = writer();
+        rainbow.write_all(&[b'a', 0xE2, 0x9C]).unwrap();
+        let output = rainbow.finish().unwrap();
+        assert_eq!(visible(&output), "a\u{FFFD}");
+    }
+}
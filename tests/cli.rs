@@ -61,6 +61,137 @@ fn version_reports_number() {
     );
 }
 
+#[test]
+fn reset_between_files_cancels_unfinished_escapes() {
+    let path = std::env::temp_dir().join(format!("lolcat-reset-{}.txt", std::process::id()));
+    std::fs::write(&path, b"a\x1b]0;unterminated title").expect("write temp file");
+    // The radial gradient reads every file before printing anything.
+    for extra in [&[][..], &["--direction=radial"]] {
+        let mut child = Command::new(binary())
+            .args(["-f", "--reset-between-files"])
+            .args(extra)
+            .arg(&path)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to spawn lolcat");
+        child
+            .stdin
+            .take()
+            .expect("no stdin")
+            .write_all(b"b\n")
+            .expect("stdin write failed");
+        let output = child.wait_with_output().expect("failed to read output");
+        assert!(output.status.success());
+        let raw = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            raw.contains("unterminated title\x18\x1b[0m\x1b[38;"),
+            "expected a cancelled title before the next file with {extra:?}: {raw:?}"
+        );
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn record_only_writes_a_cast_on_the_frame_schedule() {
+    let path = std::env::temp_dir().join(format!("lolcat-record-{}.cast", std::process::id()));
+    let mut child = Command::new(binary())
+        .args([
+            "--animate",
+            "--duration=3",
+            "--speed=10",
+            "--seed=1",
+            "--record-only",
+        ])
+        .arg(format!("--record={}", path.display()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn lolcat");
+    child
+        .stdin
+        .take()
+        .expect("no stdin")
+        .write_all(b"hi\n")
+        .expect("stdin write failed");
+    let output = child.wait_with_output().expect("failed to read output");
+    let cast = std::fs::read_to_string(&path).expect("read recording");
+    let _ = std::fs::remove_file(&path);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty(), "record-only printed output");
+    let mut lines = cast.lines();
+    assert!(lines.next().unwrap().starts_with("{\"version\": 2, "));
+    let times: Vec<&str> = lines
+        .map(|line| line[1..].split(',').next().unwrap())
+        .collect();
+    assert_eq!(times, ["0.000000", "0.100000", "0.200000", "0.300000"]);
+    assert!(cast.contains("\\u001b[38;"), "expected colors: {cast}");
+}
+
+#[test]
+fn record_keeps_piped_stdout_plain() {
+    let path = std::env::temp_dir().join(format!("lolcat-plain-{}.cast", std::process::id()));
+    let mut child = Command::new(binary())
+        .arg(format!("--record={}", path.display()))
+        .env_remove("NO_COLOR")
+        .env_remove("FORCE_COLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn lolcat");
+    child
+        .stdin
+        .take()
+        .expect("no stdin")
+        .write_all(b"plain\n")
+        .expect("stdin write failed");
+    let output = child.wait_with_output().expect("failed to read output");
+    let cast = std::fs::read_to_string(&path).expect("read recording");
+    let _ = std::fs::remove_file(&path);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"plain\n");
+    assert!(cast.contains("\\u001b[38;"), "expected colors: {cast}");
+}
+
+#[test]
+fn record_survives_a_closed_stdout_without_repeating_output() {
+    let path = std::env::temp_dir().join(format!("lolcat-closed-{}.cast", std::process::id()));
+    let mut child = Command::new(binary())
+        .args(["-f", "--seed=1"])
+        .arg(format!("--record={}", path.display()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn lolcat");
+    drop(child.stdout.take());
+    child
+        .stdin
+        .take()
+        .expect("no stdin")
+        .write_all(b"one\ntwo\nthree\n")
+        .expect("stdin write failed");
+    let status = child.wait().expect("failed to wait for lolcat");
+    let cast = std::fs::read_to_string(&path).expect("read recording");
+    let _ = std::fs::remove_file(&path);
+    assert!(status.success());
+    let recorded: String = cast
+        .lines()
+        .skip(1)
+        .map(|event| {
+            let data = event.split_once("\"o\", \"").unwrap().1;
+            let data = data.strip_suffix("\"]").unwrap();
+            strip_ansi(&data.replace("\\u001b", "\x1b").replace("\\n", "\n"))
+        })
+        .collect();
+    assert!(!recorded.is_empty(), "nothing recorded: {cast}");
+    assert!(
+        "one\ntwo\nthree\n".starts_with(&recorded),
+        "recording repeats output: {recorded:?}"
+    );
+}
+
 fn strip_ansi(input: &str) -> String {
     let mut chars = input.chars().peekable();
     let mut cleaned = String::with_capacity(input.len());
@@ -68,14 +199,14 @@ fn strip_ansi(input: &str) -> String {
         if ch == '\x1b' {
             match chars.next() {
                 Some('[') => {
-                    while let Some(c) = chars.next() {
+                    for c in chars.by_ref() {
                         if ('@'..='~').contains(&c) {
                             break;
                         }
                     }
                 }
                 Some(']') => {
-                    while let Some(c) = chars.next() {
+                    for c in chars.by_ref() {
                         if c == '\u{07}' {
                             break;
                         }
@@ -0,0 +1,41 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use neo_lolcat::{ColorMode, Config, Printer};
+
+fn binary() -> &'static str {
+    env!("CARGO_BIN_EXE_lolcat")
+}
+
+#[test]
+fn library_output_matches_binary() {
+    let input = "hello\nrainbow world\n";
+
+    let config = Config::builder().seed(42).build().unwrap();
+    let mut printer = Printer::new(config, ColorMode::Ansi256);
+    let mut expected = Vec::new();
+    printer.print_text(input, &mut expected).unwrap();
+    printer.finalize(&mut expected).unwrap();
+
+    let mut child = Command::new(binary())
+        .args(["-f", "--seed", "42"])
+        .env_remove("COLORTERM")
+        .env_remove("FORCE_COLOR")
+        .env("TERM", "xterm-256color")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn lolcat");
+    {
+        let mut stdin = child.stdin.take().expect("no stdin");
+        stdin
+            .write_all(input.as_bytes())
+            .expect("stdin write failed");
+    }
+    let output = child.wait_with_output().expect("failed to read output");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&expected)
+    );
+}